@@ -1,102 +1,104 @@
-extern mod glfw;
 extern mod glcore;
+extern mod common;
 
 use glcore::*;
+use common::buffer::Buffer;
+use common::chapter;
+use common::chapter::{Chapter, Frame};
+use common::context;
+use common::context::Surface;
+use common::gl;
+use common::reload::WatchedProgram;
+use common::shader::{Program, ProgramBuilder};
+use common::vertex::Layout;
+use common::vertex_array::VertexArray;
 
 // Vertex data
-static vertices: [GLfloat, ..6] = [
-     0.0,  0.5,
-     0.5, -0.5,
-    -0.5, -0.5
+pub struct Vertex {
+    position: [GLfloat, ..2],
+}
+
+impl Vertex {
+    fn layout() -> Layout {
+        let v = &vertices[0];
+        Layout::new(v)
+            .attrib("position", &v.position)
+    }
+}
+
+pub static vertices: [Vertex, ..3] = [
+    Vertex { position: [ 0.0,  0.5] },
+    Vertex { position: [ 0.5, -0.5] },
+    Vertex { position: [-0.5, -0.5] }
 ];
 
-// Shader sources
-static vertex_src: &'static str =
-   "#version 150\n\
-    in vec2 position;\n\
-    void main() {\n\
-       gl_Position = vec4(position, 0.0, 1.0);\n\
-    }";
+/// Makes a newly built program current and points the vertex attributes at
+/// it. Called again whenever the shaders are reloaded.
+fn configure(program: &Program) -> Result<(), ~str> {
+    program.bind();
 
-static fragment_src: &'static str =
-   "#version 150\n\
-    out vec4 outColor;\n\
-    void main() {\n\
-       outColor = vec4(1.0, 1.0, 1.0, 1.0);\n\
-    }";
+    // Specify the layout of the vertex data
+    match Vertex::layout().bind(program) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(e.to_str()),
+    }
+}
             
 fn main() {
-    do glfw::spawn {
-        // Choose a GL profile that is compatible with OS X 10.7+
-        glfw::window_hint::context_version_major(3);
-        glfw::window_hint::context_version_minor(2);
-        glfw::window_hint::opengl_profile(glfw::OPENGL_CORE_PROFILE);
-        glfw::window_hint::opengl_forward_compat(true);
-        
-        let window = glfw::Window::create(800, 600, "OpenGL", glfw::Windowed).unwrap();
-        
-        window.make_context_current();
-        
+    context::spawn(context::Config::default(), run);
+}
+
+/// Draws the chapter until the surface should close.
+pub fn run(window: &Surface) {
+    chapter::run::<Triangle>(window);
+}
+
+pub struct Triangle {
+    vao: VertexArray,
+    vbo: Buffer,
+    program: WatchedProgram,
+}
+
+impl Chapter for Triangle {
+    fn setup(_: &Surface) -> Result<Triangle, ~str> {
         // Create Vertex Array Object
-        let mut vao: GLuint = 0;
-        glGenVertexArrays(1, &vao);
-        glBindVertexArray(vao);
+        let vao = VertexArray::new();
+        vao.bind();
         
         // Create a Vertex Buffer Object and copy the vertex data to it
-        let mut vbo: GLuint = 0;
-        glGenBuffers(1, &vbo);
-        glBindBuffer(GL_ARRAY_BUFFER, vbo);
-        unsafe {
-            glBufferData(GL_ARRAY_BUFFER,
-                         (vertices.len() * sys::size_of::<GLfloat>()) as GLsizeiptr,
-                         cast::transmute(&vertices[0]),
-                         GL_STATIC_DRAW);
-        }
+        let vbo = Buffer::new(GL_ARRAY_BUFFER);
+        vbo.bind();
+        vbo.upload(&vertices, GL_STATIC_DRAW);
 
-        // Create and compile the vertex shader
-        let vertex_shader = glCreateShader(GL_VERTEX_SHADER);
-        glShaderSource(vertex_shader, 1, &str::as_c_str(vertex_src, |s|s), ptr::null());
-        glCompileShader(vertex_shader);
-        
-        // Create and compile the fragment shader
-        let fragment_shader = glCreateShader(GL_FRAGMENT_SHADER);
-        glShaderSource(fragment_shader, 1, &str::as_c_str(fragment_src, |s|s), ptr::null());
-        glCompileShader(fragment_shader);
-        
-        // Link the vertex and fragment shader into a shader program
-        let shader_program = glCreateProgram();
-        glAttachShader(shader_program, vertex_shader);
-        glAttachShader(shader_program, fragment_shader);
-        glBindFragDataLocation(shader_program, 0, str::as_c_str("outColor", |s|s));
-        glLinkProgram(shader_program);
-        glUseProgram(shader_program);
-        
-        // Specify the layout of the vertex data
-        let pos_attrib = glGetAttribLocation(shader_program, str::as_c_str("position", |s|s)) as GLuint;
-        glEnableVertexAttribArray(pos_attrib);
-        glVertexAttribPointer(pos_attrib, 2, GL_FLOAT, GL_FALSE, 0, ptr::null());
-        
-        while !window.should_close() {
-            // Poll events
-            glfw::poll_events();
-            
-            // Clear the screen to black
-            glClearColor(0.1, 0.1, 0.1, 1.0);
-            glClear(GL_COLOR_BUFFER_BIT);
-        
-            // Draw a triangle from the 3 vertices
-            glDrawArrays(GL_TRIANGLES, 0, 3);
-            
-            // Swap buffers
-            window.swap_buffers();
-        }
-        
-        glDeleteProgram(shader_program);
-        glDeleteShader(fragment_shader);
-        glDeleteShader(vertex_shader);
-        
-        glDeleteBuffers(1, &vbo);
-        
-        glDeleteVertexArrays(1, &vao);
+        // Compile the vertex and fragment shaders and link them into a
+        // program, which is rebuilt whenever the shader files are saved
+        let builder = ProgramBuilder::new()
+            .vertex_file("shaders/c2_triangle.vert")
+            .fragment_file("shaders/c2_triangle.frag")
+            .frag_data_location(0, "outColor");
+        let program = match WatchedProgram::new(builder, &vao, &vbo, configure) {
+            Ok(program) => program,
+            Err(e) => return Err(e),
+        };
+
+        Ok(Triangle { vao: vao, vbo: vbo, program: program })
     }
-}
\ No newline at end of file
+
+    fn update(&mut self, _: &Frame) {
+        // Pick up any changes to the shader files
+        self.program.reload(&self.vao, &self.vbo);
+    }
+
+    fn draw(&self, _: &Frame) {
+        let gl = gl::current();
+
+        // Clear the screen to black
+        gl.clear_color(0.1, 0.1, 0.1, 1.0);
+        gl.clear(GL_COLOR_BUFFER_BIT);
+    
+        // Draw a triangle from the 3 vertices
+        gl.draw_arrays(GL_TRIANGLES, 0, 3);
+    }
+
+    fn teardown(&mut self) {}
+}
@@ -0,0 +1,105 @@
+extern mod glcore;
+extern mod common;
+extern mod lmath;
+
+use glcore::*;
+use common::buffer::Buffer;
+use common::chapter;
+use common::chapter::{Chapter, Frame};
+use common::context;
+use common::context::Surface;
+use common::cube;
+use common::cube::vertices;
+use common::gl;
+use common::reload::WatchedProgram;
+use common::texture;
+use common::texture::Texture;
+use common::vertex_array::VertexArray;
+use lmath::mat::*;
+
+fn main() {
+    let config = context::Config::default().with_depth_stencil(24, 8);
+    context::spawn(config, run);
+}
+
+/// Draws the chapter until the surface should close. The surface needs a
+/// depth and a stencil buffer.
+pub fn run(window: &Surface) {
+    chapter::run::<DepthStencil>(window);
+}
+
+pub struct DepthStencil {
+    vao: VertexArray,
+    vbo: Buffer,
+    program: WatchedProgram,
+    kitten: Texture,
+    puppy: Texture,
+    /// The rotation of the cube
+    model: Mat4<GLfloat>,
+}
+
+impl Chapter for DepthStencil {
+    fn setup(_: &Surface) -> Result<DepthStencil, ~str> {
+        // Create Vertex Array Object
+        let vao = VertexArray::new();
+        vao.bind();
+
+        // Create a Vertex Buffer Object and copy the vertex data to it
+        let vbo = Buffer::new(GL_ARRAY_BUFFER);
+        vbo.bind();
+        vbo.upload(&vertices, GL_STATIC_DRAW);
+
+        // Compile the shaders shared by the textured chapters, this time
+        // with 3D positions and a color to tint the reflection with
+        let builder = cube::program_builder();
+        let program = match WatchedProgram::new(builder, &vao, &vbo, cube::configure) {
+            Ok(program) => program,
+            Err(e) => return Err(e),
+        };
+
+        // Load textures
+        let options = texture::Options::default();
+        let kitten = match Texture::load_resource("sample.png", &options) {
+            Ok(tex) => tex,
+            Err(e) => return Err(e.to_str()),
+        };
+        let puppy = match Texture::load_resource("sample2.png", &options) {
+            Ok(tex) => tex,
+            Err(e) => return Err(e.to_str()),
+        };
+
+        kitten.bind_to_unit(0);
+        puppy.bind_to_unit(1);
+
+        let gl = gl::current();
+        gl.enable(GL_DEPTH_TEST);
+
+        Ok(DepthStencil {
+            vao: vao, vbo: vbo, program: program,
+            kitten: kitten, puppy: puppy,
+            model: Mat4::identity(),
+        })
+    }
+
+    fn update(&mut self, frame: &Frame) {
+        // Pick up any changes to the shader files
+        self.program.reload(&self.vao, &self.vbo);
+        let program = self.program.program();
+
+        // Spin the cube around the Z axis
+        self.model = cube::spin(frame);
+        cube::set_camera(program, frame);
+    }
+
+    fn draw(&self, _: &Frame) {
+        cube::draw_scene(self.program.program(), &self.model);
+    }
+
+    fn teardown(&mut self) {
+        // Leave the state as the next chapter expects it
+        let gl = gl::current();
+        gl.disable(GL_DEPTH_TEST);
+        gl.stencil_mask(0xFF);
+        gl.active_texture(GL_TEXTURE0);
+    }
+}
@@ -0,0 +1,324 @@
+extern mod glcore;
+extern mod glfw;
+extern mod common;
+extern mod lmath;
+
+use core::libc::c_int;
+use glcore::*;
+use common::buffer::Buffer;
+use common::chapter;
+use common::chapter::{Chapter, Frame};
+use common::context;
+use common::context::Surface;
+use common::cube;
+use common::cube::vertices;
+use common::framebuffer::{Framebuffer, Renderbuffer};
+use common::gl;
+use common::reload::WatchedProgram;
+use common::shader::{Program, ProgramBuilder, ProgramCache};
+use common::texture;
+use common::texture::Texture;
+use common::uniform::Sampler;
+use common::vertex::Layout;
+use common::vertex_array::VertexArray;
+use lmath::mat::*;
+
+// Vertex data for the quad that covers the screen
+struct QuadVertex {
+    position: [GLfloat, ..2],
+    texcoord: [GLfloat, ..2],
+}
+
+impl QuadVertex {
+    fn layout() -> Layout {
+        let v = &quad_vertices[0];
+        Layout::new(v)
+            .attrib("position", &v.position)
+            .attrib("texcoord", &v.texcoord)
+    }
+}
+
+static quad_vertices: [QuadVertex, ..6] = [
+    QuadVertex { position: [-1.0,  1.0], texcoord: [0.0, 1.0] },
+    QuadVertex { position: [ 1.0,  1.0], texcoord: [1.0, 1.0] },
+    QuadVertex { position: [ 1.0, -1.0], texcoord: [1.0, 0.0] },
+    QuadVertex { position: [ 1.0, -1.0], texcoord: [1.0, 0.0] },
+    QuadVertex { position: [-1.0, -1.0], texcoord: [0.0, 0.0] },
+    QuadVertex { position: [-1.0,  1.0], texcoord: [0.0, 1.0] }
+];
+
+/// A post-processing effect applied to the whole scene.
+#[deriving(Eq)]
+pub enum Effect {
+    NoEffect,
+    Invert,
+    Grayscale,
+    Blur,
+    Edges,
+}
+
+static effects: [Effect, ..5] = [NoEffect, Invert, Grayscale, Blur, Edges];
+
+impl Effect {
+    /// The macro that picks the effect out of `shaders/post.frag`.
+    fn define(&self) -> Option<&'static str> {
+        match *self {
+            NoEffect  => None,
+            Invert    => Some("INVERT"),
+            Grayscale => Some("GRAYSCALE"),
+            Blur      => Some("BLUR"),
+            Edges     => Some("EDGES"),
+        }
+    }
+
+    /// The key that switches to the effect.
+    fn key(&self) -> c_int {
+        match *self {
+            NoEffect  => glfw::KEY_N,
+            Invert    => glfw::KEY_I,
+            Grayscale => glfw::KEY_G,
+            Blur      => glfw::KEY_B,
+            Edges     => glfw::KEY_E,
+        }
+    }
+
+    fn next(&self) -> Effect {
+        let i = effects.position_elem(self).get();
+        effects[(i + 1) % effects.len()]
+    }
+
+    fn program_builder(&self) -> ProgramBuilder {
+        let builder = ProgramBuilder::new()
+            .vertex_file("shaders/post.vert")
+            .fragment_file("shaders/post.frag")
+            .frag_data_location(0, "outColor");
+        match self.define() {
+            Some(name) => builder.define(name, "1"),
+            None => builder,
+        }
+    }
+}
+
+/// Makes a newly built post-processing program current and points the
+/// vertex attributes at it, with the quad's vertex array and buffer bound.
+fn configure_post(program: &Program) -> Result<(), ~str> {
+    program.bind();
+    program.set_uniform("texFramebuffer", Sampler(0));
+    match QuadVertex::layout().bind(program) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(e.to_str()),
+    }
+}
+
+/// The framebuffer that the scene is drawn into, with a texture to read the
+/// colors back from and a renderbuffer for depth and stencil.
+struct RenderTarget {
+    framebuffer: Framebuffer,
+    color: Texture,
+    depth_stencil: Renderbuffer,
+    width: uint,
+    height: uint,
+}
+
+impl RenderTarget {
+    fn new(width: uint, height: uint) -> Result<RenderTarget, ~str> {
+        let framebuffer = Framebuffer::new();
+        framebuffer.bind();
+
+        let color = Texture::empty(width, height, &texture::Options::default());
+        framebuffer.attach_texture(GL_COLOR_ATTACHMENT0, &color);
+
+        let depth_stencil = Renderbuffer::new(GL_DEPTH24_STENCIL8, width, height);
+        framebuffer.attach_renderbuffer(GL_DEPTH_STENCIL_ATTACHMENT, &depth_stencil);
+
+        let status = framebuffer.check();
+        Framebuffer::bind_default();
+        match status {
+            Ok(()) => Ok(RenderTarget {
+                framebuffer: framebuffer, color: color, depth_stencil: depth_stencil,
+                width: width, height: height,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn main() {
+    context::spawn(context::Config::default(), run);
+}
+
+/// Draws the chapter until the surface should close.
+pub fn run(window: &Surface) {
+    chapter::run::<Framebuffers>(window);
+}
+
+pub struct Framebuffers {
+    vao: VertexArray,
+    vbo: Buffer,
+    program: WatchedProgram,
+    kitten: Texture,
+    puppy: Texture,
+    /// The rotation of the cube
+    model: Mat4<GLfloat>,
+
+    quad_vao: VertexArray,
+    quad_vbo: Buffer,
+    /// A program for each effect that has been used
+    post_programs: ProgramCache,
+    effect: Effect,
+    post: @Program,
+    target: RenderTarget,
+}
+
+impl Framebuffers {
+    /// Switches to the program for `effect`, building it the first time.
+    fn use_effect(&mut self, effect: Effect) -> Result<(), ~str> {
+        let program = match self.post_programs.get(&effect.program_builder()) {
+            Ok(program) => program,
+            Err(e) => return Err(e.to_str()),
+        };
+
+        self.quad_vao.bind();
+        self.quad_vbo.bind();
+        match configure_post(&*program) {
+            Ok(()) => (),
+            Err(e) => return Err(e),
+        }
+
+        self.post = program;
+        self.effect = effect;
+        Ok(())
+    }
+}
+
+impl Chapter for Framebuffers {
+    fn setup(surface: &Surface) -> Result<Framebuffers, ~str> {
+        // Create the vertex array and buffer for the scene
+        let vao = VertexArray::new();
+        vao.bind();
+        let vbo = Buffer::new(GL_ARRAY_BUFFER);
+        vbo.bind();
+        vbo.upload(&vertices, GL_STATIC_DRAW);
+
+        // The scene is drawn with the shaders from the depth and stencil
+        // chapter
+        let builder = cube::program_builder();
+        let program = match WatchedProgram::new(builder, &vao, &vbo, cube::configure) {
+            Ok(program) => program,
+            Err(e) => return Err(e),
+        };
+
+        // Load textures
+        let options = texture::Options::default();
+        let kitten = match Texture::load_resource("sample.png", &options) {
+            Ok(tex) => tex,
+            Err(e) => return Err(e.to_str()),
+        };
+        let puppy = match Texture::load_resource("sample2.png", &options) {
+            Ok(tex) => tex,
+            Err(e) => return Err(e.to_str()),
+        };
+
+        // Create the vertex array and buffer for the screen quad
+        let quad_vao = VertexArray::new();
+        quad_vao.bind();
+        let quad_vbo = Buffer::new(GL_ARRAY_BUFFER);
+        quad_vbo.bind();
+        quad_vbo.upload(&quad_vertices, GL_STATIC_DRAW);
+
+        // Build the program without an effect, which later effects are
+        // cached alongside
+        let mut post_programs = ProgramCache::new();
+        let post = match post_programs.get(&NoEffect.program_builder()) {
+            Ok(program) => program,
+            Err(e) => return Err(e.to_str()),
+        };
+        match configure_post(&*post) {
+            Ok(()) => (),
+            Err(e) => return Err(e),
+        }
+
+        // Create a framebuffer the size of the surface to draw the scene
+        // into
+        let (width, height) = surface.get_size();
+        let target = match RenderTarget::new(width, height) {
+            Ok(target) => target,
+            Err(e) => return Err(e),
+        };
+
+        Ok(Framebuffers {
+            vao: vao, vbo: vbo, program: program,
+            kitten: kitten, puppy: puppy,
+            model: Mat4::identity(),
+            quad_vao: quad_vao, quad_vbo: quad_vbo,
+            post_programs: post_programs, effect: NoEffect, post: post,
+            target: target,
+        })
+    }
+
+    fn update(&mut self, frame: &Frame) {
+        // Pick up any changes to the scene's shader files, which are
+        // configured for the scene's vertex array
+        self.program.reload(&self.vao, &self.vbo);
+
+        // Space cycles through the effects, and each has a key of its own
+        let mut effect = self.effect;
+        if frame.was_pressed(glfw::KEY_SPACE) {
+            effect = effect.next();
+        }
+        for effects.each |&e| {
+            if frame.was_pressed(e.key()) { effect = e; }
+        }
+        if effect != self.effect {
+            match self.use_effect(effect) {
+                Ok(()) => (),
+                Err(e) => io::stderr().write_line(e),
+            }
+        }
+
+        // Follow the size of the surface
+        if (frame.width, frame.height) != (self.target.width, self.target.height) {
+            match RenderTarget::new(frame.width, frame.height) {
+                Ok(target) => self.target = target,
+                Err(e) => io::stderr().write_line(e),
+            }
+        }
+
+        // Spin the cube around the Z axis
+        self.model = cube::spin(frame);
+
+        let program = self.program.program();
+        program.bind();
+        cube::set_camera(program, frame);
+    }
+
+    fn draw(&self, _: &Frame) {
+        let gl = gl::current();
+
+        // Draw the scene into the framebuffer
+        self.target.framebuffer.bind();
+        self.vao.bind();
+        let program = self.program.program();
+        program.bind();
+        self.kitten.bind_to_unit(0);
+        self.puppy.bind_to_unit(1);
+
+        gl.enable(GL_DEPTH_TEST);
+        cube::draw_scene(program, &self.model);
+        gl.disable(GL_DEPTH_TEST);
+
+        // Draw the framebuffer's texture to the screen through the effect
+        Framebuffer::bind_default();
+        self.quad_vao.bind();
+        self.post.bind();
+        self.target.color.bind_to_unit(0);
+
+        gl.draw_arrays(GL_TRIANGLES, 0, 6);
+    }
+
+    fn teardown(&mut self) {
+        // Leave the state as the next chapter expects it
+        let gl = gl::current();
+        gl.stencil_mask(0xFF);
+    }
+}
@@ -0,0 +1,112 @@
+extern mod glcore;
+extern mod common;
+
+use glcore::*;
+use common::buffer::Buffer;
+use common::chapter;
+use common::chapter::{Chapter, Frame};
+use common::context;
+use common::context::Surface;
+use common::gl;
+use common::reload::WatchedProgram;
+use common::shader::{Program, ProgramBuilder};
+use common::vertex::Layout;
+use common::vertex_array::VertexArray;
+
+// Vertex data, one point for each shape
+pub struct Vertex {
+    position: [GLfloat, ..2],
+    color: [GLfloat, ..3],
+    /// The number of sides of the shape drawn around the point
+    sides: GLfloat,
+}
+
+impl Vertex {
+    fn layout() -> Layout {
+        let v = &vertices[0];
+        Layout::new(v)
+            .attrib("position", &v.position)
+            .attrib("color", &v.color)
+            .attrib("sides", &v.sides)
+    }
+}
+
+pub static vertices: [Vertex, ..4] = [
+    Vertex { position: [-0.45,  0.45], color: [1.0, 0.0, 0.0], sides:  4.0 }, // Red square
+    Vertex { position: [ 0.45,  0.45], color: [0.0, 1.0, 0.0], sides:  8.0 }, // Green octagon
+    Vertex { position: [ 0.45, -0.45], color: [0.0, 0.0, 1.0], sides: 16.0 }, // Blue 16-gon
+    Vertex { position: [-0.45, -0.45], color: [1.0, 1.0, 0.0], sides: 32.0 }  // Yellow circle
+];
+
+/// Makes a newly built program current and points the vertex attributes at
+/// it. Called again whenever the shaders are reloaded.
+fn configure(program: &Program) -> Result<(), ~str> {
+    program.bind();
+
+    // Specify the layout of the vertex data
+    match Vertex::layout().bind(program) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(e.to_str()),
+    }
+}
+
+fn main() {
+    context::spawn(context::Config::default(), run);
+}
+
+/// Draws the chapter until the surface should close.
+pub fn run(window: &Surface) {
+    chapter::run::<GeometryShader>(window);
+}
+
+pub struct GeometryShader {
+    vao: VertexArray,
+    vbo: Buffer,
+    program: WatchedProgram,
+}
+
+impl Chapter for GeometryShader {
+    fn setup(_: &Surface) -> Result<GeometryShader, ~str> {
+        // Create Vertex Array Object
+        let vao = VertexArray::new();
+        vao.bind();
+
+        // Create a Vertex Buffer Object and copy the vertex data to it
+        let vbo = Buffer::new(GL_ARRAY_BUFFER);
+        vbo.bind();
+        vbo.upload(&vertices, GL_STATIC_DRAW);
+
+        // Compile the vertex, geometry and fragment shaders and link them
+        // into a program, which is rebuilt whenever the shader files are
+        // saved
+        let builder = ProgramBuilder::new()
+            .vertex_file("shaders/c7_geometry_shader.vert")
+            .geometry_file("shaders/c7_geometry_shader.geom")
+            .fragment_file("shaders/c7_geometry_shader.frag")
+            .frag_data_location(0, "outColor");
+        let program = match WatchedProgram::new(builder, &vao, &vbo, configure) {
+            Ok(program) => program,
+            Err(e) => return Err(e),
+        };
+
+        Ok(GeometryShader { vao: vao, vbo: vbo, program: program })
+    }
+
+    fn update(&mut self, _: &Frame) {
+        // Pick up any changes to the shader files
+        self.program.reload(&self.vao, &self.vbo);
+    }
+
+    fn draw(&self, _: &Frame) {
+        let gl = gl::current();
+
+        // Clear the screen to black
+        gl.clear_color(0.1, 0.1, 0.1, 1.0);
+        gl.clear(GL_COLOR_BUFFER_BIT);
+
+        // Draw the points, which the geometry shader turns into shapes
+        gl.draw_arrays(GL_POINTS, 0, 4);
+    }
+
+    fn teardown(&mut self) {}
+}
@@ -0,0 +1,113 @@
+extern mod glcore;
+extern mod common;
+
+use glcore::*;
+use common::buffer::Buffer;
+use common::chapter;
+use common::chapter::{Chapter, Frame};
+use common::context;
+use common::context::Surface;
+use common::gl;
+use common::shader::{Program, ProgramBuilder};
+use common::vertex::Layout;
+use common::vertex_array::VertexArray;
+
+// The values the vertex shader takes the square root of
+pub static inputs: [GLfloat, ..5] = [1.0, 2.0, 3.0, 4.0, 5.0];
+
+fn main() {
+    context::spawn(context::Config::default(), run);
+}
+
+/// Draws the chapter until the surface should close.
+pub fn run(window: &Surface) {
+    chapter::run::<TransformFeedback>(window);
+}
+
+pub struct TransformFeedback {
+    vao: VertexArray,
+    vbo: Buffer,
+    /// Where the vertex shader's outputs are captured
+    tbo: Buffer,
+    program: Program,
+}
+
+impl TransformFeedback {
+    /// Runs the vertex shader over `inputs` without drawing anything, and
+    /// reads back what it wrote.
+    pub fn compute(&self) -> ~[GLfloat] {
+        let gl = gl::current();
+
+        // Stop after the vertex shader, there is nothing to rasterize
+        gl.enable(GL_RASTERIZER_DISCARD);
+
+        // Capture the outputs of every point into the buffer
+        self.tbo.bind_base(0);
+        gl.begin_transform_feedback(GL_POINTS);
+        gl.draw_arrays(GL_POINTS, 0, inputs.len());
+        gl.end_transform_feedback();
+
+        gl.disable(GL_RASTERIZER_DISCARD);
+        gl.flush();
+
+        // Copy the results back to the CPU
+        self.tbo.read::<GLfloat>(inputs.len())
+    }
+}
+
+impl Chapter for TransformFeedback {
+    fn setup(_: &Surface) -> Result<TransformFeedback, ~str> {
+        // Compile the vertex shader on its own, capturing `outValue` with
+        // transform feedback
+        let program = match ProgramBuilder::new()
+            .vertex_file("shaders/c8_transform_feedback.vert")
+            .transform_feedback_varyings(["outValue"])
+            .build() {
+            Ok(program) => program,
+            Err(e) => return Err(e.to_str()),
+        };
+        program.bind();
+
+        // Create Vertex Array Object
+        let vao = VertexArray::new();
+        vao.bind();
+
+        // Create a Vertex Buffer Object and copy the input values to it
+        let vbo = Buffer::new(GL_ARRAY_BUFFER);
+        vbo.bind();
+        vbo.upload(&inputs, GL_STATIC_DRAW);
+
+        match Layout::new(&inputs[0]).attrib("inValue", &inputs[0]).bind(&program) {
+            Ok(()) => (),
+            Err(e) => return Err(e.to_str()),
+        }
+
+        // Create a buffer with room for one output per input
+        let tbo = Buffer::new(GL_TRANSFORM_FEEDBACK_BUFFER);
+        tbo.bind();
+        tbo.allocate(inputs.len() * sys::size_of::<GLfloat>(), GL_STATIC_READ);
+
+        let chapter = TransformFeedback { vao: vao, vbo: vbo, tbo: tbo, program: program };
+
+        // Log the results once, run with `RUST_LOG=c8_transform_feedback` to
+        // see them
+        let outputs = chapter.compute();
+        for inputs.eachi |i, &input| {
+            info!("sqrt(%?) = %?", input, outputs[i]);
+        }
+
+        Ok(chapter)
+    }
+
+    fn update(&mut self, _: &Frame) {}
+
+    fn draw(&self, _: &Frame) {
+        let gl = gl::current();
+
+        // The results are logged, so there is nothing to draw
+        gl.clear_color(0.1, 0.1, 0.1, 1.0);
+        gl.clear(GL_COLOR_BUFFER_BIT);
+    }
+
+    fn teardown(&mut self) {}
+}
@@ -0,0 +1,120 @@
+//! Buffer objects.
+
+use core::num::Zero;
+use debug;
+use gl;
+use glcore::*;
+
+/// An owned buffer object, deleted when dropped.
+pub struct Buffer {
+    id: GLuint,
+    /// The binding point, eg. `GL_ARRAY_BUFFER`
+    target: GLenum,
+}
+
+impl Buffer {
+    pub fn new(target: GLenum) -> Buffer {
+        let gl = gl::current();
+        Buffer { id: check!(gl.gen_buffer()), target: target }
+    }
+
+    pub fn bind(&self) {
+        let gl = gl::current();
+        check!(gl.bind_buffer(self.target, self.id));
+    }
+
+    /// Copies `data` into the buffer, which must be bound.
+    pub fn upload<T>(&self, data: &[T], usage: GLenum) {
+        let gl = gl::current();
+        unsafe {
+            check!(gl.buffer_data(self.target,
+                                  data.len() * sys::size_of::<T>(),
+                                  cast::transmute(vec::raw::to_ptr(data)),
+                                  usage));
+        }
+    }
+
+    /// Gives the buffer, which must be bound, `size` bytes of undefined
+    /// contents for the GPU to write to.
+    pub fn allocate(&self, size: uint, usage: GLenum) {
+        let gl = gl::current();
+        check!(gl.buffer_data(self.target, size, ptr::null(), usage));
+    }
+
+    /// Binds the buffer to the indexed binding point `index` of its target,
+    /// eg. an output of transform feedback.
+    pub fn bind_base(&self, index: GLuint) {
+        let gl = gl::current();
+        check!(gl.bind_buffer_base(self.target, index, self.id));
+    }
+
+    /// Copies the first `count` values of type `T` out of the buffer, which
+    /// must be bound.
+    pub fn read<T: Copy + Zero>(&self, count: uint) -> ~[T] {
+        let gl = gl::current();
+        let data: ~[T] = vec::from_elem(count, Zero::zero());
+        unsafe {
+            check!(gl.get_buffer_sub_data(self.target, 0, count * sys::size_of::<T>(),
+                                          cast::transmute(vec::raw::to_ptr(data))));
+        }
+        data
+    }
+}
+
+impl Drop for Buffer {
+    fn finalize(&self) {
+        let gl = gl::current();
+        check!(gl.delete_buffer(self.id));
+    }
+}
+
+/// Integer types that can be used as element indices.
+pub trait Index {}
+
+impl Index for u8 {}
+impl Index for u16 {}
+impl Index for u32 {}
+
+/// The GL type enum matching the index type `T`.
+fn index_type<T: Index>() -> GLenum {
+    match sys::size_of::<T>() {
+        1 => GL_UNSIGNED_BYTE,
+        2 => GL_UNSIGNED_SHORT,
+        _ => GL_UNSIGNED_INT,
+    }
+}
+
+/// An element array buffer that remembers the type and number of its
+/// indices, so that draw calls always agree with the data.
+pub struct IndexBuffer {
+    priv buffer: Buffer,
+    /// `GL_UNSIGNED_BYTE`, `GL_UNSIGNED_SHORT` or `GL_UNSIGNED_INT`
+    index_type: GLenum,
+    /// The number of indices in the buffer
+    count: uint,
+}
+
+impl IndexBuffer {
+    /// Creates an element array buffer, binds it and copies `indices` to it.
+    /// The current vertex array keeps track of the binding.
+    pub fn new<T: Index>(indices: &[T], usage: GLenum) -> IndexBuffer {
+        let buffer = Buffer::new(GL_ELEMENT_ARRAY_BUFFER);
+        buffer.bind();
+        buffer.upload(indices, usage);
+        IndexBuffer {
+            buffer: buffer,
+            index_type: index_type::<T>(),
+            count: indices.len(),
+        }
+    }
+
+    pub fn bind(&self) {
+        self.buffer.bind();
+    }
+
+    /// Draws every index in the buffer as primitives of the given mode.
+    pub fn draw(&self, mode: GLenum) {
+        let gl = gl::current();
+        check!(gl.draw_elements(mode, self.count, self.index_type, 0));
+    }
+}
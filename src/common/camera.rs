@@ -0,0 +1,54 @@
+//! The view and projection matrices for chapters that draw in 3D.
+//!
+//! Both follow the conventions of `gluLookAt` and `gluPerspective`: the
+//! camera looks down its negative Z axis, and depth ends up between -1 at
+//! the near plane and 1 at the far plane.
+
+use glcore::GLfloat;
+use lmath::mat::Mat4;
+
+type Vec3 = [GLfloat, ..3];
+
+/// A view matrix for a camera at `eye` looking at `center`, with `up`
+/// pointing towards the top of the screen.
+pub fn look_at(eye: Vec3, center: Vec3, up: Vec3) -> Mat4<GLfloat> {
+    let f = normalize(sub(center, eye));
+    let s = normalize(cross(f, up));
+    let u = cross(s, f);
+
+    Mat4::new(s[0], u[0], -f[0], 0.0,
+              s[1], u[1], -f[1], 0.0,
+              s[2], u[2], -f[2], 0.0,
+              -dot(s, eye), -dot(u, eye), dot(f, eye), 1.0)
+}
+
+/// A perspective projection with a vertical field of view of `fovy`
+/// degrees. `aspect` is the width of the viewport divided by its height.
+pub fn perspective(fovy: GLfloat, aspect: GLfloat, near: GLfloat, far: GLfloat) -> Mat4<GLfloat> {
+    // The cotangent of half the field of view, in radians
+    let f = 1.0 / f32::tan(fovy * f32::consts::pi / 360.0);
+
+    Mat4::new(f / aspect, 0.0, 0.0,                              0.0,
+              0.0,        f,   0.0,                              0.0,
+              0.0,        0.0, (far + near) / (near - far),      -1.0,
+              0.0,        0.0, 2.0 * far * near / (near - far),  0.0)
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Vec3, b: Vec3) -> GLfloat {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1],
+     a[2] * b[0] - a[0] * b[2],
+     a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    let length = f32::sqrt(dot(a, a));
+    [a[0] / length, a[1] / length, a[2] / length]
+}
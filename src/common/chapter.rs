@@ -0,0 +1,235 @@
+//! The lifecycle shared by every chapter.
+//!
+//! A chapter creates its GL objects in `setup`, then `update` and `draw` are
+//! called once per frame until the surface should close. Anything that can
+//! host a `Surface`, such as a window, a headless test or a benchmark, can
+//! run a chapter with `run`, or switch between several with `run_switching`.
+
+use core::libc;
+use context::{Surface, KeyPresses};
+use debug;
+use gl;
+use gl::Gl;
+use glfw;
+use tracking::Tracking;
+
+/// The timing and size of the frame being drawn.
+pub struct Frame {
+    /// The time in seconds since the surface was created
+    time: f64,
+    /// The time in seconds since the previous frame, or 0 for the first
+    delta: f64,
+    /// The size of the framebuffer in pixels
+    width: uint,
+    height: uint,
+    /// The `glfw::KEY_*` codes of the keys that went down since the
+    /// previous frame
+    pressed: ~[libc::c_int],
+}
+
+impl Frame {
+    /// The width of the framebuffer divided by its height, for projection
+    /// matrices. A minimized window has no height, and an aspect of 1.
+    pub fn aspect(&self) -> f32 {
+        if self.height == 0 { 1.0 } else { self.width as f32 / self.height as f32 }
+    }
+
+    /// Whether `key` went down since the previous frame.
+    pub fn was_pressed(&self, key: libc::c_int) -> bool {
+        self.pressed.contains(&key)
+    }
+}
+
+pub trait Chapter {
+    /// Creates the chapter's GL objects, which are current when `update`
+    /// and `draw` are called.
+    fn setup(surface: &Surface) -> Result<Self, ~str>;
+
+    /// Advances any animation to the time of `frame`.
+    fn update(&mut self, frame: &Frame);
+
+    /// Draws the frame. The buffers are swapped afterwards.
+    fn draw(&self, frame: &Frame);
+
+    /// Called once after the last frame. GL objects owned by the chapter
+    /// are deleted when it is dropped, after this returns.
+    fn teardown(&mut self);
+}
+
+/// A chapter that has been set up, with its type hidden so that it can be
+/// swapped for another.
+pub trait Running {
+    /// Updates and draws a frame.
+    fn step(&mut self, frame: &Frame);
+
+    fn stop(&mut self);
+}
+
+impl<C: Chapter> Running for C {
+    fn step(&mut self, frame: &Frame) {
+        self.update(frame);
+        self.draw(frame);
+    }
+
+    fn stop(&mut self) {
+        self.teardown();
+    }
+}
+
+/// Sets up chapter `C` for `run_switching`.
+pub fn start<C: Chapter>(surface: &Surface) -> Result<~Running, ~str> {
+    let chapter: Result<C, ~str> = Chapter::setup(surface);
+    match chapter {
+        Ok(chapter) => Ok(~chapter as ~Running),
+        Err(e) => Err(e),
+    }
+}
+
+/// Times the frames drawn to a surface, and collects the keys pressed
+/// between them.
+struct Clock {
+    last: Option<f64>,
+    keys: KeyPresses,
+}
+
+impl Clock {
+    fn new() -> Clock {
+        Clock { last: None, keys: KeyPresses::new() }
+    }
+
+    fn tick(&mut self, surface: &Surface) -> Frame {
+        let time = surface.get_time();
+        let (width, height) = surface.get_size();
+
+        let mut pressed = ~[];
+        for int::range(glfw::KEY_SPACE as int, glfw::KEY_LAST as int + 1) |key| {
+            if self.keys.pressed(surface, key as libc::c_int) {
+                pressed.push(key as libc::c_int);
+            }
+        }
+
+        let frame = Frame {
+            time: time,
+            delta: match self.last { Some(last) => time - last, None => 0.0 },
+            width: width,
+            height: height,
+            pressed: pressed,
+        };
+        self.last = Some(time);
+        frame
+    }
+}
+
+/// Draws to the whole framebuffer, which may have been resized.
+fn fit_viewport(frame: &Frame) {
+    let gl = gl::current();
+    check!(gl.viewport(0, 0, frame.width, frame.height));
+}
+
+/// Sets up a chapter and draws it until the surface should close.
+pub fn run<C: Chapter>(surface: &Surface) {
+    let mut chapter: C = match Chapter::setup(surface) {
+        Ok(chapter) => chapter,
+        Err(e) => fail!(e),
+    };
+
+    let mut clock = Clock::new();
+    while !surface.should_close() {
+        surface.poll_events();
+
+        let frame = clock.tick(surface);
+        fit_viewport(&frame);
+        chapter.update(&frame);
+        chapter.draw(&frame);
+        surface.swap_buffers();
+    }
+
+    chapter.teardown();
+}
+
+/// Draws one of `count` chapters until the surface should close, starting
+/// with chapter `first`. `start` sets up a chapter by index, counting from
+/// zero.
+///
+/// The number keys switch to the chapter with that number, counting from
+/// one, and the arrow keys to the previous or next chapter. The current
+/// chapter is torn down and dropped before the next is set up, and any GL
+/// objects that it did not delete are logged as errors.
+pub fn run_switching(surface: &Surface, count: uint, first: uint,
+                     start: &fn(uint, &Surface) -> Result<~Running, ~str>) {
+    let tracking = @Tracking::new(gl::current());
+
+    do gl::with(tracking as @Gl) {
+        let keys = KeyPresses::new();
+        let mut index = first;
+        let mut current = Some(start_or_fail(start, index, surface));
+
+        let mut clock = Clock::new();
+        while !surface.should_close() {
+            surface.poll_events();
+
+            match next_index(surface, &keys, index, count) {
+                Some(next) if next != index => {
+                    {
+                        let mut chapter = current.swap_unwrap();
+                        chapter.stop();
+                    }
+
+                    for tracking.live().each |object| {
+                        error!("chapter %u did not delete %s", index + 1, object.to_str());
+                    }
+                    tracking.clear();
+
+                    index = next;
+                    current = Some(start_or_fail(start, index, surface));
+                }
+                _ => (),
+            }
+
+            let frame = clock.tick(surface);
+            fit_viewport(&frame);
+            current.get_mut_ref().step(&frame);
+            surface.swap_buffers();
+        }
+
+        current.get_mut_ref().stop();
+    }
+}
+
+fn start_or_fail(start: &fn(uint, &Surface) -> Result<~Running, ~str>,
+                 index: uint, surface: &Surface) -> ~Running {
+    match start(index, surface) {
+        Ok(chapter) => chapter,
+        Err(e) => fail!(fmt!("chapter %u: %s", index + 1, e)),
+    }
+}
+
+/// The digit on the number key that switches to the chapter at `index`: 1
+/// to 9 for the first nine chapters, then 0 for the tenth. Any later
+/// chapters are only reached with the arrow keys.
+pub fn number_key(index: uint) -> Option<uint> {
+    if index < 9 {
+        Some(index + 1)
+    } else if index == 9 {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// The chapter to switch to, if a key was pressed.
+fn next_index(surface: &Surface, keys: &KeyPresses, index: uint, count: uint) -> Option<uint> {
+    if keys.pressed(surface, glfw::KEY_RIGHT) {
+        return Some((index + 1) % count);
+    }
+    if keys.pressed(surface, glfw::KEY_LEFT) {
+        return Some((index + count - 1) % count);
+    }
+    for uint::range(0, count) |i| {
+        match number_key(i) {
+            Some(digit) if keys.pressed(surface, glfw::KEY_0 + digit as libc::c_int) => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
@@ -0,0 +1,218 @@
+//! Window and GL context creation.
+
+use core::hashmap::HashSet;
+use core::libc::c_int;
+use debug;
+use glfw;
+use headless;
+
+/// The OpenGL profile to request when creating a context.
+#[deriving(Eq)]
+pub enum Profile {
+    AnyProfile,
+    CoreProfile,
+    CompatProfile,
+}
+
+/// The parameters used to create a window and its GL context.
+pub struct Config {
+    /// The `(major, minor)` GL version to request
+    version: (uint, uint),
+    profile: Profile,
+    forward_compat: bool,
+    width: uint,
+    height: uint,
+    title: ~str,
+    /// Wait for the vertical blank when swapping buffers
+    vsync: bool,
+    /// The number of samples per pixel for multisample antialiasing, or 0
+    /// to turn it off. Ignored by headless surfaces.
+    samples: uint,
+    /// Use the whole of the primary monitor instead of opening a window
+    fullscreen: bool,
+    /// The bits per pixel of the depth buffer, or 0 for none
+    depth_bits: uint,
+    /// The bits per pixel of the stencil buffer, or 0 for none
+    stencil_bits: uint,
+    /// Request a debug context and check for GL errors. See the `debug`
+    /// module.
+    debug: bool,
+    /// Render this many frames offscreen instead of opening a window. See
+    /// the `headless` module.
+    headless: Option<uint>,
+}
+
+/// The window, or offscreen surface, that a chapter draws to.
+pub trait Surface {
+    fn should_close(&self) -> bool;
+
+    /// Processes pending window events.
+    fn poll_events(&self);
+
+    fn swap_buffers(&self);
+
+    /// The time in seconds since the surface was created.
+    fn get_time(&self) -> f64;
+
+    /// The size of the framebuffer in pixels.
+    fn get_size(&self) -> (uint, uint);
+
+    /// Whether `key`, one of the `glfw::KEY_*` codes, is held down.
+    fn is_key_down(&self, key: c_int) -> bool;
+}
+
+impl Surface for glfw::Window {
+    fn should_close(&self) -> bool { self.should_close() }
+    fn poll_events(&self) { glfw::poll_events(); }
+    fn swap_buffers(&self) { self.swap_buffers(); }
+    fn get_time(&self) -> f64 { glfw::get_time() }
+
+    fn get_size(&self) -> (uint, uint) {
+        let (width, height) = self.get_size();
+        (width as uint, height as uint)
+    }
+
+    fn is_key_down(&self, key: c_int) -> bool { self.get_key(key) == glfw::PRESS }
+}
+
+/// Turns keys being held down into single presses.
+pub struct KeyPresses {
+    priv down: @mut HashSet<c_int>,
+}
+
+impl KeyPresses {
+    pub fn new() -> KeyPresses {
+        KeyPresses { down: @mut HashSet::new() }
+    }
+
+    /// Whether `key` has gone down since this was last asked.
+    pub fn pressed(&self, surface: &Surface, key: c_int) -> bool {
+        if surface.is_key_down(key) {
+            self.down.insert(key)
+        } else {
+            self.down.remove(&key);
+            false
+        }
+    }
+}
+
+impl Config {
+    /// An 800x600 window with a forward compatible GL 3.2 core profile. This
+    /// is compatible with OS X 10.7+. Vsync is on, multisampling is off and
+    /// there is no depth or stencil buffer.
+    ///
+    /// Debugging is enabled if the `GL_DEBUG` environment variable is set.
+    /// Setting `GL_HEADLESS` to a number of frames renders them offscreen
+    /// instead of opening a window.
+    pub fn default() -> Config {
+        Config {
+            version: (3, 2),
+            profile: CoreProfile,
+            forward_compat: true,
+            width: 800,
+            height: 600,
+            title: ~"OpenGL",
+            vsync: true,
+            samples: 0,
+            fullscreen: false,
+            depth_bits: 0,
+            stencil_bits: 0,
+            debug: os::getenv("GL_DEBUG").is_some(),
+            headless: os::getenv("GL_HEADLESS").map(|frames| {
+                uint::from_str(*frames).get_or_default(1)
+            }),
+        }
+    }
+
+    pub fn with_version(self, major: uint, minor: uint) -> Config {
+        Config { version: (major, minor), ..self }
+    }
+
+    pub fn with_profile(self, profile: Profile) -> Config {
+        Config { profile: profile, ..self }
+    }
+
+    pub fn with_forward_compat(self, forward_compat: bool) -> Config {
+        Config { forward_compat: forward_compat, ..self }
+    }
+
+    pub fn with_size(self, width: uint, height: uint) -> Config {
+        Config { width: width, height: height, ..self }
+    }
+
+    pub fn with_title(self, title: &str) -> Config {
+        Config { title: title.to_owned(), ..self }
+    }
+
+    pub fn with_vsync(self, vsync: bool) -> Config {
+        Config { vsync: vsync, ..self }
+    }
+
+    pub fn with_samples(self, samples: uint) -> Config {
+        Config { samples: samples, ..self }
+    }
+
+    pub fn with_fullscreen(self, fullscreen: bool) -> Config {
+        Config { fullscreen: fullscreen, ..self }
+    }
+
+    /// Requests a depth buffer and a stencil buffer with the given number
+    /// of bits per pixel.
+    pub fn with_depth_stencil(self, depth_bits: uint, stencil_bits: uint) -> Config {
+        Config { depth_bits: depth_bits, stencil_bits: stencil_bits, ..self }
+    }
+
+    pub fn with_debug(self, debug: bool) -> Config {
+        Config { debug: debug, ..self }
+    }
+
+    pub fn with_headless(self, frames: Option<uint>) -> Config {
+        Config { headless: frames, ..self }
+    }
+}
+
+/// Creates the window, or headless surface, described by `config` and makes
+/// its context current before handing the surface to `f`.
+pub fn spawn(config: Config, f: ~fn(&Surface)) {
+    if config.headless.is_some() {
+        headless::spawn(config, f);
+    } else {
+        spawn_window(config, f);
+    }
+}
+
+/// Starts GLFW and opens a window described by `config`.
+fn spawn_window(config: Config, f: ~fn(&Surface)) {
+    do glfw::spawn {
+        let (major, minor) = config.version;
+        glfw::window_hint::context_version_major(major);
+        glfw::window_hint::context_version_minor(minor);
+        glfw::window_hint::opengl_profile(match config.profile {
+            AnyProfile    => glfw::OPENGL_ANY_PROFILE,
+            CoreProfile   => glfw::OPENGL_CORE_PROFILE,
+            CompatProfile => glfw::OPENGL_COMPAT_PROFILE,
+        });
+        glfw::window_hint::opengl_forward_compat(config.forward_compat);
+        glfw::window_hint::opengl_debug_context(config.debug);
+        glfw::window_hint::samples(config.samples);
+        glfw::window_hint::depth_bits(config.depth_bits);
+        glfw::window_hint::stencil_bits(config.stencil_bits);
+
+        let mode = if config.fullscreen {
+            glfw::FullScreen(glfw::get_primary_monitor())
+        } else {
+            glfw::Windowed
+        };
+        let window = glfw::Window::create(config.width, config.height,
+                                          config.title, mode).unwrap();
+
+        window.make_context_current();
+        glfw::set_swap_interval(if config.vsync { 1 } else { 0 });
+
+        if config.debug {
+            debug::enable(glfw::get_proc_address);
+        }
+
+        f(&window as &Surface);
+    }
+}
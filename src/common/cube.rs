@@ -0,0 +1,183 @@
+//! The textured cube standing on a black floor that the depth and stencil
+//! and framebuffer chapters draw.
+
+use camera;
+use chapter::Frame;
+use debug;
+use gl;
+use glcore::*;
+use lmath::vec3::*;
+use lmath::mat::*;
+use lmath::quat::*;
+use numeric::radians;
+use shader::{Program, ProgramBuilder};
+use uniform::Sampler;
+use vertex::Layout;
+
+pub struct Vertex {
+    position: [GLfloat, ..3],
+    color: [GLfloat, ..3],
+    texcoord: [GLfloat, ..2],
+}
+
+impl Vertex {
+    pub fn layout() -> Layout {
+        let v = &vertices[0];
+        Layout::new(v)
+            .attrib("position", &v.position)
+            .attrib("color", &v.color)
+            .attrib("texcoord", &v.texcoord)
+    }
+}
+
+/// The 36 vertices of the cube, followed by the 6 of the floor.
+pub static vertices: [Vertex, ..42] = [
+    // Cube
+    Vertex { position: [-0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+    Vertex { position: [ 0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [ 0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [-0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [-0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+
+    Vertex { position: [-0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+    Vertex { position: [ 0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [ 0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [-0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [-0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+
+    Vertex { position: [-0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [-0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [-0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [-0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [-0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+    Vertex { position: [-0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+
+    Vertex { position: [ 0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [ 0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [ 0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [ 0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+
+    Vertex { position: [-0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [ 0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [ 0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [ 0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [-0.5, -0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+    Vertex { position: [-0.5, -0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+
+    Vertex { position: [-0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [ 0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [ 0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [-0.5,  0.5,  0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 0.0] },
+    Vertex { position: [-0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0], texcoord: [0.0, 1.0] },
+
+    // Floor, which is black so that the reflection is darkened
+    Vertex { position: [-1.0, -1.0, -0.5], color: [0.0, 0.0, 0.0], texcoord: [0.0, 0.0] },
+    Vertex { position: [ 1.0, -1.0, -0.5], color: [0.0, 0.0, 0.0], texcoord: [1.0, 0.0] },
+    Vertex { position: [ 1.0,  1.0, -0.5], color: [0.0, 0.0, 0.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [ 1.0,  1.0, -0.5], color: [0.0, 0.0, 0.0], texcoord: [1.0, 1.0] },
+    Vertex { position: [-1.0,  1.0, -0.5], color: [0.0, 0.0, 0.0], texcoord: [0.0, 1.0] },
+    Vertex { position: [-1.0, -1.0, -0.5], color: [0.0, 0.0, 0.0], texcoord: [0.0, 0.0] }
+];
+
+/// The shaders shared by the textured chapters, with 3D positions and a
+/// color to tint the reflection with.
+pub fn program_builder() -> ProgramBuilder {
+    ProgramBuilder::new()
+        .vertex_file("shaders/textured.vert")
+        .fragment_file("shaders/textured.frag")
+        .define("USE_3D", "1")
+        .define("USE_TRANSFORM", "1")
+        .define("USE_OVERRIDE_COLOR", "1")
+        .define("USE_COLOR", "1")
+        .define("MIX_TEXTURES", "1")
+        .frag_data_location(0, "outColor")
+}
+
+/// Makes a newly built program current and points the vertex attributes at
+/// it, with the vertex array and buffer holding `vertices` bound.
+pub fn configure(program: &Program) -> Result<(), ~str> {
+    program.bind();
+
+    // Tell the samplers which texture units to read
+    program.set_uniform("texKitten", Sampler(0));
+    program.set_uniform("texPuppy", Sampler(1));
+
+    // Only the reflection is tinted
+    program.set_uniform("overrideColor", [1.0 as GLfloat, 1.0, 1.0]);
+
+    // Specify the layout of the vertex data
+    match Vertex::layout().bind(program) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(e.to_str()),
+    }
+}
+
+/// The model matrix of the cube at the time of `frame`, spinning half a
+/// turn a second around the Z axis.
+pub fn spin(frame: &Frame) -> Mat4<GLfloat> {
+    quat::from_angle_axis(
+        radians(frame.time * 180.0) as GLfloat,
+        &vec3::unit_z()
+    ).to_mat3().to_mat4()
+}
+
+/// Sets the view and projection matrices of `program`, which must be
+/// current, for the size of `frame`.
+pub fn set_camera(program: &Program, frame: &Frame) {
+    // Look down at the cube from above one corner, with Z pointing up
+    let view = camera::look_at([2.5, 2.5, 2.0], [0.0, 0.0, 0.0], [0.0, 0.0, 1.0]);
+    program.set_uniform("view", view);
+
+    let proj = camera::perspective(45.0, frame.aspect(), 1.0, 10.0);
+    program.set_uniform("proj", proj);
+}
+
+/// Clears the bound framebuffer and draws the cube turned by `model`, the
+/// floor and the reflection of the cube in it. The framebuffer needs a
+/// depth and a stencil buffer, depth testing must be enabled, and
+/// `program` must be current with the textures bound.
+pub fn draw_scene(program: &Program, model: &Mat4<GLfloat>) {
+    let gl = gl::current();
+
+    // Clear to black
+    check!(gl.clear_color(0.1, 0.1, 0.1, 1.0));
+    check!(gl.clear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT));
+
+    // Draw cube
+    program.set_uniform("model", *model);
+    check!(gl.draw_arrays(GL_TRIANGLES, 0, 36));
+
+    check!(gl.enable(GL_STENCIL_TEST));
+
+    // Draw floor, setting the stencil to 1 wherever it is but leaving the
+    // depth buffer alone so that the reflection can be drawn below
+    check!(gl.stencil_func(GL_ALWAYS, 1, 0xFF));
+    check!(gl.stencil_op(GL_KEEP, GL_KEEP, GL_REPLACE));
+    check!(gl.stencil_mask(0xFF));
+    check!(gl.depth_mask(false));
+    check!(gl.clear(GL_STENCIL_BUFFER_BIT));
+
+    check!(gl.draw_arrays(GL_TRIANGLES, 36, 6));
+
+    // Draw cube reflection, only where the stencil is 1
+    check!(gl.stencil_func(GL_EQUAL, 1, 0xFF));
+    check!(gl.stencil_mask(0x00));
+    check!(gl.depth_mask(true));
+
+    // Mirror the cube in the floor, which is half a unit below it
+    let mirror = Mat4::new(1.0, 0.0,  0.0, 0.0,
+                           0.0, 1.0,  0.0, 0.0,
+                           0.0, 0.0, -1.0, 0.0,
+                           0.0, 0.0, -1.0, 1.0);
+    program.set_uniform("model", model.mul_m(&mirror));
+    program.set_uniform("overrideColor", [0.3 as GLfloat, 0.3, 0.3]);
+    check!(gl.draw_arrays(GL_TRIANGLES, 0, 36));
+    program.set_uniform("overrideColor", [1.0 as GLfloat, 1.0, 1.0]);
+
+    check!(gl.disable(GL_STENCIL_TEST));
+}
@@ -0,0 +1,104 @@
+//! Opt-in checking of GL errors.
+//!
+//! When debugging is enabled every GL call made through the `check!` macro is
+//! followed by `glGetError`, and errors are logged with the call and where it
+//! was made. If the context supports `KHR_debug` the driver's own messages
+//! are logged as well.
+
+use core::local_data;
+use core::libc::c_void;
+use gl;
+use glcore::*;
+
+static GL_DEBUG_OUTPUT: GLenum = 0x92E0;
+static GL_DEBUG_OUTPUT_SYNCHRONOUS: GLenum = 0x8242;
+static GL_DEBUG_SEVERITY_HIGH: GLenum = 0x9146;
+static GL_DEBUG_SEVERITY_MEDIUM: GLenum = 0x9147;
+static GL_DEBUG_SEVERITY_LOW: GLenum = 0x9148;
+
+fn enabled_key(_: @bool) {}
+
+/// Whether errors are being checked on this task.
+pub fn is_enabled() -> bool {
+    unsafe {
+        match local_data::local_data_get(enabled_key) {
+            Some(enabled) => *enabled,
+            None => false,
+        }
+    }
+}
+
+/// Starts checking errors for the context that is current on this task.
+/// `get_proc_address` loads GL functions from the context's platform library.
+pub fn enable(get_proc_address: &fn(&str) -> *c_void) {
+    unsafe { local_data::local_data_set(enabled_key, @true); }
+
+    if extension_supported("GL_KHR_debug") {
+        install_callback(get_proc_address);
+    } else {
+        info!("GL_KHR_debug is not supported, only glGetError will be checked");
+    }
+}
+
+/// Logs any errors raised by `call`, made at `file:line`. Called by the
+/// `check!` macro.
+pub fn check(call: &str, file: &str, line: uint) {
+    if !is_enabled() { return; }
+
+    loop {
+        let err = gl::current().get_error();
+        if err == GL_NO_ERROR { break; }
+        error!("%s:%u: %s raised %s", file, line, call, error_name(err));
+    }
+}
+
+fn error_name(err: GLenum) -> ~str {
+    match err {
+        GL_INVALID_ENUM                  => ~"GL_INVALID_ENUM",
+        GL_INVALID_VALUE                 => ~"GL_INVALID_VALUE",
+        GL_INVALID_OPERATION             => ~"GL_INVALID_OPERATION",
+        GL_INVALID_FRAMEBUFFER_OPERATION => ~"GL_INVALID_FRAMEBUFFER_OPERATION",
+        GL_OUT_OF_MEMORY                 => ~"GL_OUT_OF_MEMORY",
+        _                                => fmt!("error 0x%x", err as uint),
+    }
+}
+
+fn extension_supported(name: &str) -> bool {
+    let gl = gl::current();
+    let count = gl.get_integer_v(GL_NUM_EXTENSIONS);
+    for uint::range(0, count as uint) |i| {
+        if gl.get_string_i(GL_EXTENSIONS, i as GLuint).as_slice() == name { return true; }
+    }
+    false
+}
+
+/// Routes `KHR_debug` messages to the logger. The function is loaded at
+/// runtime because it is not part of the GL 3.2 core API.
+fn install_callback(get_proc_address: &fn(&str) -> *c_void) {
+    let proc_addr = get_proc_address("glDebugMessageCallback");
+    if proc_addr.is_null() { return; }
+
+    unsafe {
+        let debug_message_callback: extern "C" fn(*u8, *c_void) = cast::transmute(proc_addr);
+        debug_message_callback(log_message, ptr::null());
+    }
+
+    // Only debug contexts send messages by default
+    let gl = gl::current();
+    gl.enable(GL_DEBUG_OUTPUT);
+
+    // Report messages on the thread that made the call, so that they are
+    // logged next to the call
+    gl.enable(GL_DEBUG_OUTPUT_SYNCHRONOUS);
+}
+
+extern fn log_message(_source: GLenum, _ty: GLenum, id: GLuint, severity: GLenum,
+                      length: GLsizei, message: *GLchar, _user: *c_void) {
+    let message = unsafe { str::raw::from_buf_len(message as *u8, length as uint) };
+    match severity {
+        GL_DEBUG_SEVERITY_HIGH   => error!("GL debug message %u: %s", id as uint, message),
+        GL_DEBUG_SEVERITY_MEDIUM => warn!("GL debug message %u: %s", id as uint, message),
+        GL_DEBUG_SEVERITY_LOW    => info!("GL debug message %u: %s", id as uint, message),
+        _                        => debug!("GL debug message %u: %s", id as uint, message),
+    }
+}
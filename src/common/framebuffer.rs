@@ -0,0 +1,100 @@
+//! Framebuffer and renderbuffer objects, for drawing into textures.
+
+use debug;
+use gl;
+use glcore::*;
+use texture::Texture;
+
+/// An owned renderbuffer, deleted when dropped.
+pub struct Renderbuffer {
+    id: GLuint,
+}
+
+impl Renderbuffer {
+    /// Creates a renderbuffer with storage for `width` by `height` pixels
+    /// of `internal_format`, such as `GL_DEPTH24_STENCIL8`.
+    pub fn new(internal_format: GLenum, width: uint, height: uint) -> Renderbuffer {
+        let gl = gl::current();
+        let renderbuffer = Renderbuffer { id: check!(gl.gen_renderbuffer()) };
+        check!(gl.bind_renderbuffer(GL_RENDERBUFFER, renderbuffer.id));
+        check!(gl.renderbuffer_storage(GL_RENDERBUFFER, internal_format, width, height));
+        renderbuffer
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn finalize(&self) {
+        let gl = gl::current();
+        check!(gl.delete_renderbuffer(self.id));
+    }
+}
+
+/// An owned framebuffer object, deleted when dropped.
+pub struct Framebuffer {
+    id: GLuint,
+}
+
+impl Framebuffer {
+    pub fn new() -> Framebuffer {
+        let gl = gl::current();
+        Framebuffer { id: check!(gl.gen_framebuffer()) }
+    }
+
+    /// Draws to this framebuffer instead of the surface.
+    pub fn bind(&self) {
+        let gl = gl::current();
+        check!(gl.bind_framebuffer(GL_FRAMEBUFFER, self.id));
+    }
+
+    /// Draws to the surface again.
+    pub fn bind_default() {
+        let gl = gl::current();
+        check!(gl.bind_framebuffer(GL_FRAMEBUFFER, 0));
+    }
+
+    /// Attaches a texture, such as to `GL_COLOR_ATTACHMENT0`. The
+    /// framebuffer must be bound.
+    pub fn attach_texture(&self, attachment: GLenum, texture: &Texture) {
+        let gl = gl::current();
+        check!(gl.framebuffer_texture_2d(GL_FRAMEBUFFER, attachment, GL_TEXTURE_2D, texture.id, 0));
+    }
+
+    /// Attaches a renderbuffer, such as to `GL_DEPTH_STENCIL_ATTACHMENT`.
+    /// The framebuffer must be bound.
+    pub fn attach_renderbuffer(&self, attachment: GLenum, renderbuffer: &Renderbuffer) {
+        let gl = gl::current();
+        check!(gl.framebuffer_renderbuffer(GL_FRAMEBUFFER, attachment, GL_RENDERBUFFER,
+                                           renderbuffer.id));
+    }
+
+    /// Checks that the framebuffer, which must be bound, has a working set
+    /// of attachments.
+    pub fn check(&self) -> Result<(), ~str> {
+        let gl = gl::current();
+        match check!(gl.check_framebuffer_status(GL_FRAMEBUFFER)) {
+            GL_FRAMEBUFFER_COMPLETE => Ok(()),
+            status => Err(fmt!("Framebuffer %u is incomplete: %s",
+                               self.id as uint, status_name(status))),
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn finalize(&self) {
+        let gl = gl::current();
+        check!(gl.delete_framebuffer(self.id));
+    }
+}
+
+fn status_name(status: GLenum) -> ~str {
+    match status {
+        GL_FRAMEBUFFER_UNDEFINED                     => ~"GL_FRAMEBUFFER_UNDEFINED",
+        GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT         => ~"GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT",
+        GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => ~"GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT",
+        GL_FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER        => ~"GL_FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER",
+        GL_FRAMEBUFFER_INCOMPLETE_READ_BUFFER        => ~"GL_FRAMEBUFFER_INCOMPLETE_READ_BUFFER",
+        GL_FRAMEBUFFER_UNSUPPORTED                   => ~"GL_FRAMEBUFFER_UNSUPPORTED",
+        GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE        => ~"GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE",
+        other => fmt!("status 0x%x", other as uint),
+    }
+}
@@ -0,0 +1,451 @@
+//! The GL calls made by the library and the chapters, behind a trait.
+//!
+//! Everything goes through the backend that is current on the task, which
+//! is normally `Native`. Tests can swap in a `recording::Recording` to
+//! check the calls that some setup code makes without a GL driver.
+
+use core::local_data;
+use core::libc;
+use core::libc::c_void;
+use glcore::*;
+
+pub trait Gl {
+    // Buffers
+    fn gen_buffer(&self) -> GLuint;
+    fn delete_buffer(&self, buffer: GLuint);
+    fn bind_buffer(&self, target: GLenum, buffer: GLuint);
+    /// `size` is in bytes.
+    fn buffer_data(&self, target: GLenum, size: uint, data: *c_void, usage: GLenum);
+    /// Binds `buffer` to one of the indexed binding points of `target`.
+    fn bind_buffer_base(&self, target: GLenum, index: GLuint, buffer: GLuint);
+    /// Copies `size` bytes from `offset` in the buffer bound to `target`.
+    fn get_buffer_sub_data(&self, target: GLenum, offset: uint, size: uint, data: *mut c_void);
+
+    // Vertex arrays
+    fn gen_vertex_array(&self) -> GLuint;
+    fn delete_vertex_array(&self, array: GLuint);
+    fn bind_vertex_array(&self, array: GLuint);
+    fn enable_vertex_attrib_array(&self, index: GLuint);
+    /// `stride` and `offset` are in bytes.
+    fn vertex_attrib_pointer(&self, index: GLuint, size: GLint, ty: GLenum,
+                             normalized: bool, stride: uint, offset: uint);
+
+    // Shaders and programs
+    fn create_shader(&self, ty: GLenum) -> GLuint;
+    fn shader_source(&self, shader: GLuint, src: &str);
+    fn compile_shader(&self, shader: GLuint);
+    fn get_shader_iv(&self, shader: GLuint, pname: GLenum) -> GLint;
+    fn get_shader_info_log(&self, shader: GLuint) -> ~str;
+    fn delete_shader(&self, shader: GLuint);
+    fn create_program(&self) -> GLuint;
+    fn attach_shader(&self, program: GLuint, shader: GLuint);
+    fn detach_shader(&self, program: GLuint, shader: GLuint);
+    fn bind_frag_data_location(&self, program: GLuint, color: GLuint, name: &str);
+    fn transform_feedback_varyings(&self, program: GLuint, varyings: &[~str], mode: GLenum);
+    fn link_program(&self, program: GLuint);
+    fn get_program_iv(&self, program: GLuint, pname: GLenum) -> GLint;
+    fn get_program_info_log(&self, program: GLuint) -> ~str;
+    fn use_program(&self, program: GLuint);
+    fn delete_program(&self, program: GLuint);
+
+    // Attributes and uniforms
+    fn get_attrib_location(&self, program: GLuint, name: &str) -> GLint;
+    /// The name and type of an active attribute.
+    fn get_active_attrib(&self, program: GLuint, index: GLuint) -> (~str, GLenum);
+    fn get_uniform_location(&self, program: GLuint, name: &str) -> GLint;
+    /// The name and type of an active uniform.
+    fn get_active_uniform(&self, program: GLuint, index: GLuint) -> (~str, GLenum);
+    fn uniform_1f(&self, location: GLint, x: GLfloat);
+    fn uniform_1i(&self, location: GLint, x: GLint);
+    fn uniform_2f(&self, location: GLint, x: GLfloat, y: GLfloat);
+    fn uniform_3f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat);
+    fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat);
+    /// `value` holds one column major matrix.
+    fn uniform_matrix_4fv(&self, location: GLint, value: &[GLfloat]);
+
+    // Textures
+    fn gen_texture(&self) -> GLuint;
+    fn delete_texture(&self, texture: GLuint);
+    fn bind_texture(&self, target: GLenum, texture: GLuint);
+    fn active_texture(&self, unit: GLenum);
+    fn tex_image_2d(&self, target: GLenum, level: GLint, internal_format: GLenum,
+                    width: uint, height: uint, format: GLenum, ty: GLenum, data: *c_void);
+    fn tex_parameter_i(&self, target: GLenum, pname: GLenum, param: GLint);
+    fn tex_parameter_iv(&self, target: GLenum, pname: GLenum, params: &[GLint]);
+    fn generate_mipmap(&self, target: GLenum);
+    fn pixel_store_i(&self, pname: GLenum, param: GLint);
+    fn read_pixels(&self, x: GLint, y: GLint, width: uint, height: uint,
+                   format: GLenum, ty: GLenum, data: *mut c_void);
+
+    // Framebuffers
+    fn gen_framebuffer(&self) -> GLuint;
+    fn delete_framebuffer(&self, framebuffer: GLuint);
+    fn bind_framebuffer(&self, target: GLenum, framebuffer: GLuint);
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, tex_target: GLenum,
+                              texture: GLuint, level: GLint);
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum;
+    fn gen_renderbuffer(&self) -> GLuint;
+    fn delete_renderbuffer(&self, renderbuffer: GLuint);
+    fn bind_renderbuffer(&self, target: GLenum, renderbuffer: GLuint);
+    fn renderbuffer_storage(&self, target: GLenum, internal_format: GLenum,
+                            width: uint, height: uint);
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum,
+                                rb_target: GLenum, renderbuffer: GLuint);
+
+    // Drawing
+    fn clear_color(&self, r: GLfloat, g: GLfloat, b: GLfloat, a: GLfloat);
+    fn clear(&self, mask: GLbitfield);
+    fn draw_arrays(&self, mode: GLenum, first: GLint, count: uint);
+    /// `offset` is in bytes from the start of the element array buffer.
+    fn draw_elements(&self, mode: GLenum, count: uint, ty: GLenum, offset: uint);
+    fn begin_transform_feedback(&self, mode: GLenum);
+    fn end_transform_feedback(&self);
+
+    // State
+    fn enable(&self, cap: GLenum);
+    fn disable(&self, cap: GLenum);
+    fn viewport(&self, x: GLint, y: GLint, width: uint, height: uint);
+    fn depth_mask(&self, flag: bool);
+    fn stencil_func(&self, func: GLenum, reference: GLint, mask: GLuint);
+    fn stencil_op(&self, sfail: GLenum, dpfail: GLenum, dppass: GLenum);
+    fn stencil_mask(&self, mask: GLuint);
+    fn flush(&self);
+    fn finish(&self);
+    fn get_error(&self) -> GLenum;
+    fn get_integer_v(&self, pname: GLenum) -> GLint;
+    fn get_string(&self, name: GLenum) -> ~str;
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> ~str;
+}
+
+/// Calls straight through to the driver.
+pub struct Native;
+
+impl Gl for Native {
+    fn gen_buffer(&self) -> GLuint {
+        let id: GLuint = 0;
+        glGenBuffers(1, &id);
+        id
+    }
+
+    fn delete_buffer(&self, buffer: GLuint) { glDeleteBuffers(1, &buffer); }
+    fn bind_buffer(&self, target: GLenum, buffer: GLuint) { glBindBuffer(target, buffer); }
+
+    fn buffer_data(&self, target: GLenum, size: uint, data: *c_void, usage: GLenum) {
+        glBufferData(target, size as GLsizeiptr, data, usage);
+    }
+
+    fn bind_buffer_base(&self, target: GLenum, index: GLuint, buffer: GLuint) {
+        glBindBufferBase(target, index, buffer);
+    }
+
+    fn get_buffer_sub_data(&self, target: GLenum, offset: uint, size: uint, data: *mut c_void) {
+        glGetBufferSubData(target, offset as GLintptr, size as GLsizeiptr, data);
+    }
+
+    fn gen_vertex_array(&self) -> GLuint {
+        let id: GLuint = 0;
+        glGenVertexArrays(1, &id);
+        id
+    }
+
+    fn delete_vertex_array(&self, array: GLuint) { glDeleteVertexArrays(1, &array); }
+    fn bind_vertex_array(&self, array: GLuint) { glBindVertexArray(array); }
+    fn enable_vertex_attrib_array(&self, index: GLuint) { glEnableVertexAttribArray(index); }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, size: GLint, ty: GLenum,
+                             normalized: bool, stride: uint, offset: uint) {
+        unsafe {
+            glVertexAttribPointer(index, size, ty, normalized as GLboolean,
+                                  stride as GLsizei, cast::transmute(offset));
+        }
+    }
+
+    fn create_shader(&self, ty: GLenum) -> GLuint { glCreateShader(ty) }
+
+    fn shader_source(&self, shader: GLuint, src: &str) {
+        do str::as_c_str(src) |s| {
+            glShaderSource(shader, 1, &s, ptr::null());
+        }
+    }
+
+    fn compile_shader(&self, shader: GLuint) { glCompileShader(shader); }
+
+    fn get_shader_iv(&self, shader: GLuint, pname: GLenum) -> GLint {
+        let value: GLint = 0;
+        glGetShaderiv(shader, pname, &value);
+        value
+    }
+
+    fn get_shader_info_log(&self, shader: GLuint) -> ~str {
+        let len = self.get_shader_iv(shader, GL_INFO_LOG_LENGTH);
+        if len <= 1 { return ~""; }
+
+        let mut buf = vec::from_elem(len as uint, 0u8);
+        unsafe {
+            glGetShaderInfoLog(shader, len as GLsizei, ptr::null(), cast::transmute(&buf[0]));
+        }
+        buf.pop(); // Trailing NUL
+        str::from_bytes(buf)
+    }
+
+    fn delete_shader(&self, shader: GLuint) { glDeleteShader(shader); }
+    fn create_program(&self) -> GLuint { glCreateProgram() }
+    fn attach_shader(&self, program: GLuint, shader: GLuint) { glAttachShader(program, shader); }
+    fn detach_shader(&self, program: GLuint, shader: GLuint) { glDetachShader(program, shader); }
+
+    fn bind_frag_data_location(&self, program: GLuint, color: GLuint, name: &str) {
+        do str::as_c_str(name) |s| {
+            glBindFragDataLocation(program, color, s);
+        }
+    }
+
+    fn transform_feedback_varyings(&self, program: GLuint, varyings: &[~str], mode: GLenum) {
+        // NUL terminated copies of the names, which must outlive the call
+        let names = varyings.map(|v| str::to_bytes(*v) + [0u8]);
+        let pointers = names.map(|name| vec::raw::to_ptr(*name) as *GLchar);
+        unsafe {
+            glTransformFeedbackVaryings(program, pointers.len() as GLsizei,
+                                        cast::transmute(vec::raw::to_ptr(pointers)), mode);
+        }
+    }
+
+    fn link_program(&self, program: GLuint) { glLinkProgram(program); }
+
+    fn get_program_iv(&self, program: GLuint, pname: GLenum) -> GLint {
+        let value: GLint = 0;
+        glGetProgramiv(program, pname, &value);
+        value
+    }
+
+    fn get_program_info_log(&self, program: GLuint) -> ~str {
+        let len = self.get_program_iv(program, GL_INFO_LOG_LENGTH);
+        if len <= 1 { return ~""; }
+
+        let mut buf = vec::from_elem(len as uint, 0u8);
+        unsafe {
+            glGetProgramInfoLog(program, len as GLsizei, ptr::null(), cast::transmute(&buf[0]));
+        }
+        buf.pop(); // Trailing NUL
+        str::from_bytes(buf)
+    }
+
+    fn use_program(&self, program: GLuint) { glUseProgram(program); }
+    fn delete_program(&self, program: GLuint) { glDeleteProgram(program); }
+
+    fn get_attrib_location(&self, program: GLuint, name: &str) -> GLint {
+        do str::as_c_str(name) |s| {
+            glGetAttribLocation(program, s)
+        }
+    }
+
+    fn get_active_attrib(&self, program: GLuint, index: GLuint) -> (~str, GLenum) {
+        let max_len = self.get_program_iv(program, GL_ACTIVE_ATTRIBUTE_MAX_LENGTH);
+        let len: GLsizei = 0;
+        let size: GLint = 0;
+        let ty: GLenum = 0;
+        let mut buf = vec::from_elem(max_len as uint, 0u8);
+        unsafe {
+            glGetActiveAttrib(program, index, max_len as GLsizei,
+                              &len, &size, &ty, cast::transmute(&buf[0]));
+        }
+        buf.truncate(len as uint);
+        (str::from_bytes(buf), ty)
+    }
+
+    fn get_uniform_location(&self, program: GLuint, name: &str) -> GLint {
+        do str::as_c_str(name) |s| {
+            glGetUniformLocation(program, s)
+        }
+    }
+
+    fn get_active_uniform(&self, program: GLuint, index: GLuint) -> (~str, GLenum) {
+        let max_len = self.get_program_iv(program, GL_ACTIVE_UNIFORM_MAX_LENGTH);
+        let len: GLsizei = 0;
+        let size: GLint = 0;
+        let ty: GLenum = 0;
+        let mut buf = vec::from_elem(max_len as uint, 0u8);
+        unsafe {
+            glGetActiveUniform(program, index, max_len as GLsizei,
+                               &len, &size, &ty, cast::transmute(&buf[0]));
+        }
+        buf.truncate(len as uint);
+        (str::from_bytes(buf), ty)
+    }
+
+    fn uniform_1f(&self, location: GLint, x: GLfloat) { glUniform1f(location, x); }
+    fn uniform_1i(&self, location: GLint, x: GLint) { glUniform1i(location, x); }
+    fn uniform_2f(&self, location: GLint, x: GLfloat, y: GLfloat) { glUniform2f(location, x, y); }
+
+    fn uniform_3f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat) {
+        glUniform3f(location, x, y, z);
+    }
+
+    fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat) {
+        glUniform4f(location, x, y, z, w);
+    }
+
+    fn uniform_matrix_4fv(&self, location: GLint, value: &[GLfloat]) {
+        glUniformMatrix4fv(location, 1, GL_FALSE, vec::raw::to_ptr(value));
+    }
+
+    fn gen_texture(&self) -> GLuint {
+        let id: GLuint = 0;
+        glGenTextures(1, &id);
+        id
+    }
+
+    fn delete_texture(&self, texture: GLuint) { glDeleteTextures(1, &texture); }
+    fn bind_texture(&self, target: GLenum, texture: GLuint) { glBindTexture(target, texture); }
+    fn active_texture(&self, unit: GLenum) { glActiveTexture(unit); }
+
+    fn tex_image_2d(&self, target: GLenum, level: GLint, internal_format: GLenum,
+                    width: uint, height: uint, format: GLenum, ty: GLenum, data: *c_void) {
+        glTexImage2D(target, level, internal_format as GLint,
+                     width as GLsizei, height as GLsizei, 0, format, ty, data);
+    }
+
+    fn tex_parameter_i(&self, target: GLenum, pname: GLenum, param: GLint) {
+        glTexParameteri(target, pname, param);
+    }
+
+    fn tex_parameter_iv(&self, target: GLenum, pname: GLenum, params: &[GLint]) {
+        glTexParameteriv(target, pname, vec::raw::to_ptr(params));
+    }
+
+    fn generate_mipmap(&self, target: GLenum) { glGenerateMipmap(target); }
+    fn pixel_store_i(&self, pname: GLenum, param: GLint) { glPixelStorei(pname, param); }
+
+    fn read_pixels(&self, x: GLint, y: GLint, width: uint, height: uint,
+                   format: GLenum, ty: GLenum, data: *mut c_void) {
+        unsafe {
+            glReadPixels(x, y, width as GLsizei, height as GLsizei,
+                         format, ty, cast::transmute(data));
+        }
+    }
+
+    fn gen_framebuffer(&self) -> GLuint {
+        let id: GLuint = 0;
+        glGenFramebuffers(1, &id);
+        id
+    }
+
+    fn delete_framebuffer(&self, framebuffer: GLuint) { glDeleteFramebuffers(1, &framebuffer); }
+
+    fn bind_framebuffer(&self, target: GLenum, framebuffer: GLuint) {
+        glBindFramebuffer(target, framebuffer);
+    }
+
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, tex_target: GLenum,
+                              texture: GLuint, level: GLint) {
+        glFramebufferTexture2D(target, attachment, tex_target, texture, level);
+    }
+
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum {
+        glCheckFramebufferStatus(target)
+    }
+
+    fn gen_renderbuffer(&self) -> GLuint {
+        let id: GLuint = 0;
+        glGenRenderbuffers(1, &id);
+        id
+    }
+
+    fn delete_renderbuffer(&self, renderbuffer: GLuint) { glDeleteRenderbuffers(1, &renderbuffer); }
+
+    fn bind_renderbuffer(&self, target: GLenum, renderbuffer: GLuint) {
+        glBindRenderbuffer(target, renderbuffer);
+    }
+
+    fn renderbuffer_storage(&self, target: GLenum, internal_format: GLenum,
+                            width: uint, height: uint) {
+        glRenderbufferStorage(target, internal_format, width as GLsizei, height as GLsizei);
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum,
+                                rb_target: GLenum, renderbuffer: GLuint) {
+        glFramebufferRenderbuffer(target, attachment, rb_target, renderbuffer);
+    }
+
+    fn clear_color(&self, r: GLfloat, g: GLfloat, b: GLfloat, a: GLfloat) { glClearColor(r, g, b, a); }
+    fn clear(&self, mask: GLbitfield) { glClear(mask); }
+
+    fn draw_arrays(&self, mode: GLenum, first: GLint, count: uint) {
+        glDrawArrays(mode, first, count as GLsizei);
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: uint, ty: GLenum, offset: uint) {
+        unsafe {
+            glDrawElements(mode, count as GLsizei, ty, cast::transmute(offset));
+        }
+    }
+
+    fn begin_transform_feedback(&self, mode: GLenum) { glBeginTransformFeedback(mode); }
+    fn end_transform_feedback(&self) { glEndTransformFeedback(); }
+
+    fn enable(&self, cap: GLenum) { glEnable(cap); }
+    fn disable(&self, cap: GLenum) { glDisable(cap); }
+
+    fn viewport(&self, x: GLint, y: GLint, width: uint, height: uint) {
+        glViewport(x, y, width as GLsizei, height as GLsizei);
+    }
+
+    fn depth_mask(&self, flag: bool) {
+        glDepthMask(if flag { GL_TRUE } else { GL_FALSE });
+    }
+
+    fn stencil_func(&self, func: GLenum, reference: GLint, mask: GLuint) {
+        glStencilFunc(func, reference, mask);
+    }
+
+    fn stencil_op(&self, sfail: GLenum, dpfail: GLenum, dppass: GLenum) {
+        glStencilOp(sfail, dpfail, dppass);
+    }
+
+    fn stencil_mask(&self, mask: GLuint) { glStencilMask(mask); }
+
+    fn flush(&self) { glFlush(); }
+    fn finish(&self) { glFinish(); }
+    fn get_error(&self) -> GLenum { glGetError() }
+
+    fn get_integer_v(&self, pname: GLenum) -> GLint {
+        let value: GLint = 0;
+        glGetIntegerv(pname, &value);
+        value
+    }
+
+    fn get_string(&self, name: GLenum) -> ~str {
+        unsafe { str::raw::from_c_str(glGetString(name) as *libc::c_char) }
+    }
+
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> ~str {
+        unsafe { str::raw::from_c_str(glGetStringi(name, index) as *libc::c_char) }
+    }
+}
+
+fn backend_key(_: @@Gl) {}
+
+/// The backend that GL calls on this task go through.
+pub fn current() -> @Gl {
+    unsafe {
+        match local_data::local_data_get(backend_key) {
+            Some(backend) => *backend,
+            None => @Native as @Gl,
+        }
+    }
+}
+
+/// Makes `backend` current on this task while `f` runs.
+pub fn with<T>(backend: @Gl, f: &fn() -> T) -> T {
+    let previous = unsafe { local_data::local_data_get(backend_key) };
+    unsafe { local_data::local_data_set(backend_key, @backend); }
+
+    let result = f();
+
+    unsafe {
+        match previous {
+            Some(previous) => local_data::local_data_set(backend_key, previous),
+            None => local_data::local_data_pop(backend_key),
+        };
+    }
+    result
+}
@@ -0,0 +1,277 @@
+//! A preprocessor run over shader sources before they are compiled.
+//!
+//! `#include "name"` is replaced by the resource `shaders/name`, and the
+//! defines that a program is built with are inserted as `#define` lines
+//! after the `#version` line, so that one file can be built in several
+//! permutations with `#ifdef`. Everything else is left to the GLSL compiler.
+//!
+//! `retarget` then rewrites the `#version` line for the GLSL dialect of the
+//! context that was created, so that the same sources run on GL 3.2 core,
+//! GL 3.3 and later, and GLES 3.0.
+//!
+//! Both leave `#line` directives after the lines they add, so that the
+//! driver reports errors at the lines of the files they are in. Each file
+//! is a source string of its own: 0 for the stage, and 1 on for its
+//! includes in the order they were first included. Mesa always reports
+//! source string 0, so there the lines of an include can't be told apart
+//! from those of the stage.
+
+use gl;
+use glcore::*;
+use resources;
+
+/// The GLSL dialect that shaders are compiled as.
+#[deriving(Eq)]
+pub enum Target {
+    /// GLSL 1.50 for GL 3.2, where fragment outputs are bound with
+    /// `glBindFragDataLocation`
+    Glsl150,
+    /// GLSL 3.30 for GL 3.3 and later, with `layout(location)` outputs
+    Glsl330,
+    /// GLSL ES 3.00 for GLES 3.0, which has no `glBindFragDataLocation`
+    Essl300,
+}
+
+impl Target {
+    /// The dialect of the current context.
+    pub fn current() -> Target {
+        Target::from_version(gl::current().get_string(GL_VERSION))
+    }
+
+    /// The dialect for a `GL_VERSION` string, such as `3.3.0 NVIDIA 331.20`
+    /// or `OpenGL ES 3.0 Mesa 10.1`.
+    pub fn from_version(version: &str) -> Target {
+        if version.starts_with("OpenGL ES") { return Essl300; }
+
+        let number = match str::words(version) {
+            [ref number, .._] => copy *number,
+            [] => ~"",
+        };
+        let parts = str::split_char(number, '.');
+        if parts.len() < 2 { return Glsl150; }
+        match (uint::from_str(parts[0]), uint::from_str(parts[1])) {
+            (Some(major), Some(minor)) if major > 3 || (major == 3 && minor >= 3) => Glsl330,
+            _ => Glsl150,
+        }
+    }
+
+    /// Whether fragment outputs are given their color numbers in the
+    /// source, instead of with `glBindFragDataLocation` before linking.
+    pub fn has_output_locations(&self) -> bool {
+        *self != Glsl150
+    }
+
+    /// A `#line` directive that makes the next line number `line` of
+    /// source string `string`.
+    fn line_directive(&self, line: uint, string: uint) -> ~str {
+        match *self {
+            // GLSL 1.50 numbers the line after `#line n` as n + 1
+            Glsl150 => fmt!("#line %u %u\n", if line > 0 { line - 1 } else { 0 }, string),
+            Glsl330 | Essl300 => fmt!("#line %u %u\n", line, string),
+        }
+    }
+
+    /// The lines that start a shader in this dialect.
+    fn header(&self) -> &'static str {
+        match *self {
+            Glsl150 => "#version 150\n",
+            Glsl330 => "#version 330\n",
+            // Fragment shaders have no default float precision on GLES
+            Essl300 => "#version 300 es\nprecision highp float;\n",
+        }
+    }
+}
+
+/// A source with its includes expanded.
+pub struct Preprocessed {
+    /// The expanded source, whose `#line` directives follow GLSL 3.30 until
+    /// it is passed to `retarget`
+    source: ~str,
+    /// The resources that were included, directly or not
+    includes: ~[~str],
+    /// The text of each source string: the source that was preprocessed,
+    /// then each of `includes`
+    sources: ~[~str],
+}
+
+/// Expands the includes in `src` and inserts `defines`, as `(name, value)`
+/// pairs.
+pub fn preprocess(src: &str, defines: &[(~str, ~str)]) -> Result<Preprocessed, ~str> {
+    let mut expanded = ~"";
+    let mut includes = ~[];
+    let mut sources = ~[src.to_owned()];
+    match expand(src, 0, &mut ~[], &mut includes, &mut sources, &mut expanded) {
+        Ok(()) => (),
+        Err(e) => return Err(e),
+    }
+
+    // `#version` must come before anything but comments
+    let mut source = ~"";
+    let mut defined = defines.is_empty();
+    let mut number = 1;
+    let mut string = 0;
+    for str::each_line(expanded) |line| {
+        source.push_str(line);
+        source.push_char('\n');
+        if !defined && line.trim().starts_with("#version") {
+            push_defines(&mut source, defines);
+            source.push_str(Glsl330.line_directive(number + 1, string));
+            defined = true;
+        }
+        let (next_number, next_string) = next_line(line, number, string);
+        number = next_number;
+        string = next_string;
+    }
+    if !defined {
+        let mut with_defines = ~"";
+        push_defines(&mut with_defines, defines);
+        with_defines.push_str(Glsl330.line_directive(1, 0));
+        with_defines.push_str(source);
+        source = with_defines;
+    }
+
+    Ok(Preprocessed { source: source, includes: includes, sources: sources })
+}
+
+fn push_defines(source: &mut ~str, defines: &[(~str, ~str)]) {
+    for defines.each |&(ref name, ref value)| {
+        source.push_str(fmt!("#define %s %s\n", *name, *value));
+    }
+}
+
+/// Appends `src`, which is source string `string`, to `out` with its
+/// includes replaced. `stack` holds the files being included, to catch
+/// files that include themselves.
+fn expand(src: &str, string: uint, stack: &mut ~[~str], includes: &mut ~[~str],
+          sources: &mut ~[~str], out: &mut ~str) -> Result<(), ~str> {
+    let mut number = 0;
+    for str::each_line(src) |line| {
+        number += 1;
+        let name = match include_name(line) {
+            Some(name) => name,
+            None => {
+                out.push_str(line);
+                out.push_char('\n');
+                loop;
+            }
+        };
+
+        let resource = ~"shaders/" + name;
+        if stack.contains(&resource) {
+            return Err(fmt!("%s includes itself", resource));
+        }
+        let included = match resources::read_str(resource) {
+            Ok(included) => included,
+            Err(e) => return Err(fmt!("failed to include %s: %s", resource, e)),
+        };
+        if !includes.contains(&resource) {
+            includes.push(copy resource);
+            sources.push(copy included);
+        }
+        let included_string = includes.position_elem(&resource).get() + 1;
+
+        out.push_str(Glsl330.line_directive(1, included_string));
+        stack.push(resource);
+        match expand(included, included_string, stack, includes, sources, out) {
+            Ok(()) => (),
+            Err(e) => return Err(e),
+        }
+        stack.pop();
+        out.push_str(Glsl330.line_directive(number + 1, string));
+    }
+    Ok(())
+}
+
+/// The number and source string of the line after `line`, which is line
+/// `number` of source string `string`.
+fn next_line(line: &str, number: uint, string: uint) -> (uint, uint) {
+    match line_directive(line) {
+        Some((number, Some(string))) => (number, string),
+        Some((number, None)) => (number, string),
+        None => (number + 1, string),
+    }
+}
+
+/// The line and any source string numbers of a `#line` directive, which
+/// are taken to follow GLSL 3.30.
+fn line_directive(line: &str) -> Option<(uint, Option<uint>)> {
+    let line = line.trim();
+    if !line.starts_with("#line") { return None; }
+
+    match str::words(line.slice(5, line.len())) {
+        [ref number] => uint::from_str(*number).map(|&n| (n, None)),
+        [ref number, ref string] => match (uint::from_str(*number), uint::from_str(*string)) {
+            (Some(number), Some(string)) => Some((number, Some(string))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The file named by an `#include "name"` line.
+fn include_name(line: &str) -> Option<~str> {
+    let line = line.trim();
+    if !line.starts_with("#include") { return None; }
+
+    let name = line.slice(8, line.len()).trim();
+    if name.len() >= 2 && name.starts_with("\"") && name.ends_with("\"") {
+        Some(name.slice(1, name.len() - 1).to_owned())
+    } else {
+        None
+    }
+}
+
+/// Rewrites `src` for `target`. Any `#version` line is replaced, or the
+/// header added if there is none, and `#line` directives are rewritten for
+/// the dialect. `outputs` are the `(color, name)` pairs for a fragment
+/// shader's outputs, which targets that support it declare with
+/// `layout(location = color)`.
+pub fn retarget(src: &str, target: Target, outputs: &[(GLuint, ~str)]) -> ~str {
+    let mut out = ~"";
+    let mut has_version = false;
+    let mut number = 1;
+    let mut string = 0;
+    for str::each_line(src) |line| {
+        let (next_number, next_string) = next_line(line, number, string);
+        if line_directive(line).is_some() {
+            out.push_str(target.line_directive(next_number, next_string));
+        } else if line.trim().starts_with("#version") {
+            out.push_str(target.header());
+            // The precision line would put every later line out by one
+            if target == Essl300 {
+                out.push_str(target.line_directive(number + 1, string));
+            }
+            has_version = true;
+        } else {
+            if target.has_output_locations() {
+                match output_name(line) {
+                    Some(name) => match outputs.find(|&(_, ref output)| *output == name) {
+                        Some((color, _)) => out.push_str(fmt!("layout(location = %u) ", color as uint)),
+                        None => (),
+                    },
+                    None => (),
+                }
+            }
+            out.push_str(line);
+            out.push_char('\n');
+        }
+        number = next_number;
+        string = next_string;
+    }
+
+    if has_version {
+        out
+    } else {
+        target.header().to_owned() + target.line_directive(1, 0) + out
+    }
+}
+
+/// The variable declared by an `out vec4 name;` line.
+fn output_name(line: &str) -> Option<~str> {
+    match str::words(line) {
+        [ref qualifier, _, ref name] if *qualifier == ~"out" && name.ends_with(";") => {
+            Some(name.slice(0, name.len() - 1).to_owned())
+        }
+        _ => None,
+    }
+}
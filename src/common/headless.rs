@@ -0,0 +1,169 @@
+//! Offscreen rendering without a window or display, using Mesa's OSMesa
+//! software renderer.
+//!
+//! This lets the chapters run on machines without a GPU, such as CI
+//! servers. Mesa must be built with OSMesa support, and the chapters linked
+//! against `libOSMesa` so that the GL functions come from the software
+//! renderer.
+
+use core::libc;
+use core::libc::{c_int, c_void};
+use context::{Config, Surface, CoreProfile, CompatProfile, AnyProfile};
+use debug;
+use gl;
+use glcore::*;
+
+type OSMesaContext = *c_void;
+
+static OSMESA_FORMAT: c_int = 0x22;
+static OSMESA_DEPTH_BITS: c_int = 0x30;
+static OSMESA_STENCIL_BITS: c_int = 0x31;
+static OSMESA_PROFILE: c_int = 0x33;
+static OSMESA_CORE_PROFILE: c_int = 0x34;
+static OSMESA_COMPAT_PROFILE: c_int = 0x35;
+static OSMESA_CONTEXT_MAJOR_VERSION: c_int = 0x36;
+static OSMESA_CONTEXT_MINOR_VERSION: c_int = 0x37;
+
+/// The time that passes between frames, so that animations are the same on
+/// every run.
+static FRAME_TIME: f64 = 1.0 / 60.0;
+
+#[link_args = "-lOSMesa"]
+extern mod osmesa {
+    fn OSMesaCreateContextAttribs(attribs: *c_int, sharelist: OSMesaContext) -> OSMesaContext;
+    fn OSMesaMakeCurrent(ctx: OSMesaContext, buffer: *c_void, ty: GLenum,
+                         width: GLsizei, height: GLsizei) -> GLboolean;
+    fn OSMesaGetProcAddress(name: *libc::c_char) -> *c_void;
+    fn OSMesaDestroyContext(ctx: OSMesaContext);
+}
+
+/// An offscreen context rendering into memory. It reports that it should
+/// close after a fixed number of frames.
+pub struct Context {
+    priv handle: OSMesaContext,
+    /// The color buffer that OSMesa renders into. It must not move while
+    /// the context exists.
+    priv buffer: ~[u8],
+    width: uint,
+    height: uint,
+    priv frame: @mut uint,
+    priv frames: uint,
+}
+
+impl Context {
+    /// Creates a context with the version, profile, size and depth and
+    /// stencil bits in `config`, which renders `frames` frames before it
+    /// should close.
+    pub fn create(config: &Config, frames: uint) -> Result<Context, ~str> {
+        let (major, minor) = config.version;
+        let mut attribs = ~[
+            OSMESA_FORMAT, GL_RGBA as c_int,
+            OSMESA_DEPTH_BITS, config.depth_bits as c_int,
+            OSMESA_STENCIL_BITS, config.stencil_bits as c_int,
+            OSMESA_CONTEXT_MAJOR_VERSION, major as c_int,
+            OSMESA_CONTEXT_MINOR_VERSION, minor as c_int,
+        ];
+        match config.profile {
+            CoreProfile => attribs.push_all([OSMESA_PROFILE, OSMESA_CORE_PROFILE]),
+            CompatProfile => attribs.push_all([OSMESA_PROFILE, OSMESA_COMPAT_PROFILE]),
+            AnyProfile => (),
+        }
+        attribs.push(0);
+
+        let handle = unsafe {
+            osmesa::OSMesaCreateContextAttribs(vec::raw::to_ptr(attribs), ptr::null())
+        };
+        if handle.is_null() {
+            return Err(fmt!("Failed to create an OSMesa context for GL %u.%u", major, minor));
+        }
+
+        let context = Context {
+            handle: handle,
+            buffer: vec::from_elem(config.width * config.height * 4, 0u8),
+            width: config.width,
+            height: config.height,
+            frame: @mut 0,
+            frames: frames,
+        };
+        if context.make_current() {
+            Ok(context)
+        } else {
+            Err(~"Failed to make the OSMesa context current")
+        }
+    }
+
+    pub fn make_current(&self) -> bool {
+        unsafe {
+            osmesa::OSMesaMakeCurrent(self.handle,
+                                      cast::transmute(vec::raw::to_ptr(self.buffer)),
+                                      GL_UNSIGNED_BYTE,
+                                      self.width as GLsizei,
+                                      self.height as GLsizei) == GL_TRUE
+        }
+    }
+
+    /// Reads back the pixels drawn so far. See `read_pixels`.
+    pub fn read_pixels(&self) -> ~[u8] {
+        read_pixels(self.width, self.height)
+    }
+}
+
+impl Surface for Context {
+    fn should_close(&self) -> bool { *self.frame >= self.frames }
+    fn poll_events(&self) {}
+
+    fn swap_buffers(&self) {
+        gl::current().finish();
+        *self.frame += 1;
+    }
+
+    fn get_time(&self) -> f64 { *self.frame as f64 * FRAME_TIME }
+    fn get_size(&self) -> (uint, uint) { (self.width, self.height) }
+    fn is_key_down(&self, _: c_int) -> bool { false }
+}
+
+impl Drop for Context {
+    fn finalize(&self) {
+        unsafe { osmesa::OSMesaDestroyContext(self.handle); }
+    }
+}
+
+/// Loads a GL function from OSMesa.
+pub fn get_proc_address(name: &str) -> *c_void {
+    unsafe { str::as_c_str(name, |s| osmesa::OSMesaGetProcAddress(s)) }
+}
+
+/// Reads the RGBA pixels of the current framebuffer, with the top row first.
+pub fn read_pixels(width: uint, height: uint) -> ~[u8] {
+    let row = width * 4;
+    let pixels = vec::from_elem(row * height, 0u8);
+    let gl = gl::current();
+    check!(gl.pixel_store_i(GL_PACK_ALIGNMENT, 1));
+    unsafe {
+        check!(gl.read_pixels(0, 0, width, height,
+                              GL_RGBA, GL_UNSIGNED_BYTE, cast::transmute(&pixels[0])));
+    }
+
+    // GL counts rows from the bottom
+    let mut flipped = vec::with_capacity(row * height);
+    for uint::range(0, height) |y| {
+        let start = (height - 1 - y) * row;
+        flipped.push_all(pixels.slice(start, start + row));
+    }
+    flipped
+}
+
+/// Creates a headless context described by `config` and hands it to `f`.
+pub fn spawn(config: Config, f: ~fn(&Surface)) {
+    let frames = config.headless.get_or_default(1);
+    let context = match Context::create(&config, frames) {
+        Ok(context) => context,
+        Err(e) => fail!(e),
+    };
+
+    if config.debug {
+        debug::enable(get_proc_address);
+    }
+
+    f(&context as &Surface);
+}
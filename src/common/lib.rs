@@ -0,0 +1,41 @@
+#[link(name = "common", vers = "0.1")];
+#[crate_type = "lib"];
+
+//! Code shared between the tutorial chapters.
+
+extern mod glfw;
+extern mod glcore;
+extern mod lmath;
+extern mod numeric;
+extern mod stb_image;
+
+/// Makes a GL call, then checks `glGetError` if debugging is enabled. See
+/// the `debug` module.
+macro_rules! check(
+    ($call:expr) => ({
+        let result = $call;
+        debug::check(stringify!($call), file!(), line!());
+        result
+    })
+)
+
+pub mod buffer;
+pub mod camera;
+pub mod chapter;
+pub mod context;
+pub mod cube;
+pub mod debug;
+pub mod framebuffer;
+pub mod gl;
+pub mod glsl;
+pub mod headless;
+pub mod png;
+pub mod recording;
+pub mod reload;
+pub mod resources;
+pub mod shader;
+pub mod texture;
+pub mod tracking;
+pub mod uniform;
+pub mod vertex;
+pub mod vertex_array;
@@ -0,0 +1,94 @@
+//! A minimal PNG encoder for saving rendered frames.
+//!
+//! Images are written as 8-bit RGBA without compression, which keeps the
+//! encoder small. Use stb_image to read them back.
+
+/// Writes `pixels`, in RGBA with the top row first, to a PNG file.
+pub fn write(path: &Path, width: uint, height: uint, pixels: &[u8]) -> Result<(), ~str> {
+    assert!(pixels.len() == width * height * 4);
+
+    let writer = match io::file_writer(path, [io::Create, io::Truncate]) {
+        Ok(writer) => writer,
+        Err(e) => return Err(e),
+    };
+
+    writer.write([0x89, 'P' as u8, 'N' as u8, 'G' as u8, 0x0D, 0x0A, 0x1A, 0x0A]);
+
+    let mut header = ~[];
+    push_u32(&mut header, width as u32);
+    push_u32(&mut header, height as u32);
+    // 8 bits per channel, RGBA, default compression, filtering and no
+    // interlacing
+    header.push_all([8, 6, 0, 0, 0]);
+    write_chunk(writer, "IHDR", header);
+
+    // Every scanline starts with its filter type, which is always none
+    let row = width * 4;
+    let mut scanlines = vec::with_capacity((row + 1) * height);
+    for uint::range(0, height) |y| {
+        scanlines.push(0);
+        scanlines.push_all(pixels.slice(y * row, (y + 1) * row));
+    }
+    write_chunk(writer, "IDAT", zlib_stored(scanlines));
+
+    write_chunk(writer, "IEND", []);
+    Ok(())
+}
+
+fn write_chunk(writer: @io::Writer, kind: &str, data: &[u8]) {
+    let mut chunk = ~[];
+    push_u32(&mut chunk, data.len() as u32);
+    chunk.push_all(kind.to_bytes());
+    chunk.push_all(data);
+    // The checksum covers the kind and the data, but not the length
+    let crc = crc32(chunk.slice(4, chunk.len()));
+    push_u32(&mut chunk, crc);
+    writer.write(chunk);
+}
+
+/// Wraps `data` in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> ~[u8] {
+    static MAX_BLOCK: uint = 65535;
+
+    let mut stream = ~[0x78, 0x01];
+    let mut start = 0;
+    loop {
+        let end = uint::min(start + MAX_BLOCK, data.len());
+        let len = end - start;
+        let last = end == data.len();
+
+        stream.push(if last { 1 } else { 0 });
+        stream.push_all([len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        stream.push_all(data.slice(start, end));
+
+        if last { break; }
+        start = end;
+    }
+    push_u32(&mut stream, adler32(data));
+    stream
+}
+
+fn push_u32(buf: &mut ~[u8], value: u32) {
+    buf.push_all([(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]);
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFF_u32;
+    for data.each |&byte| {
+        crc ^= byte as u32;
+        for 8.times {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1_u32;
+    let mut b = 0_u32;
+    for data.each |&byte| {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,382 @@
+//! A GL backend that records calls instead of making them.
+//!
+//! Queries return answers that keep the library working: shaders always
+//! compile, programs always link with no active attributes or uniforms,
+//! framebuffers are always complete and there are never any errors. Buffers
+//! read back as whatever the destination already held.
+//!
+//! Object names count up from 1, and the context claims to be GL 3.2 unless
+//! `with_version` says otherwise.
+
+use core::libc::c_void;
+use gl::Gl;
+use glcore::*;
+
+/// A GL call that changes some state, with the arguments that matter.
+#[deriving(Eq)]
+pub enum Call {
+    GenBuffer(GLuint),
+    DeleteBuffer(GLuint),
+    BindBuffer(GLenum, GLuint),
+    /// `(target, size in bytes, usage)`
+    BufferData(GLenum, uint, GLenum),
+    /// `(target, index, buffer)`
+    BindBufferBase(GLenum, GLuint, GLuint),
+    /// `(target, offset, size)`
+    GetBufferSubData(GLenum, uint, uint),
+    GenVertexArray(GLuint),
+    DeleteVertexArray(GLuint),
+    BindVertexArray(GLuint),
+    EnableVertexAttribArray(GLuint),
+    /// `(index, size, type, normalized, stride, offset)`
+    VertexAttribPointer(GLuint, GLint, GLenum, bool, uint, uint),
+    /// `(type, name)`
+    CreateShader(GLenum, GLuint),
+    ShaderSource(GLuint, ~str),
+    CompileShader(GLuint),
+    DeleteShader(GLuint),
+    CreateProgram(GLuint),
+    /// `(program, shader)`
+    AttachShader(GLuint, GLuint),
+    /// `(program, shader)`
+    DetachShader(GLuint, GLuint),
+    /// `(program, color, name)`
+    BindFragDataLocation(GLuint, GLuint, ~str),
+    /// `(program, varyings, mode)`
+    TransformFeedbackVaryings(GLuint, ~[~str], GLenum),
+    LinkProgram(GLuint),
+    UseProgram(GLuint),
+    DeleteProgram(GLuint),
+    /// `(location, values)`, for every float and matrix uniform
+    UniformFloats(GLint, ~[GLfloat]),
+    /// `(location, value)`
+    UniformInt(GLint, GLint),
+    GenTexture(GLuint),
+    DeleteTexture(GLuint),
+    BindTexture(GLenum, GLuint),
+    ActiveTexture(GLenum),
+    /// `(target, level, internal format, width, height, format, type)`
+    TexImage2D(GLenum, GLint, GLenum, uint, uint, GLenum, GLenum),
+    TexParameteri(GLenum, GLenum, GLint),
+    TexParameteriv(GLenum, GLenum, ~[GLint]),
+    GenerateMipmap(GLenum),
+    PixelStorei(GLenum, GLint),
+    ReadPixels(GLint, GLint, uint, uint),
+    GenFramebuffer(GLuint),
+    DeleteFramebuffer(GLuint),
+    BindFramebuffer(GLenum, GLuint),
+    /// `(target, attachment, texture target, texture, level)`
+    FramebufferTexture2D(GLenum, GLenum, GLenum, GLuint, GLint),
+    GenRenderbuffer(GLuint),
+    DeleteRenderbuffer(GLuint),
+    BindRenderbuffer(GLenum, GLuint),
+    /// `(target, internal format, width, height)`
+    RenderbufferStorage(GLenum, GLenum, uint, uint),
+    /// `(target, attachment, renderbuffer target, renderbuffer)`
+    FramebufferRenderbuffer(GLenum, GLenum, GLenum, GLuint),
+    ClearColor(GLfloat, GLfloat, GLfloat, GLfloat),
+    Clear(GLbitfield),
+    /// `(mode, first, count)`
+    DrawArrays(GLenum, GLint, uint),
+    /// `(mode, count, type, offset)`
+    DrawElements(GLenum, uint, GLenum, uint),
+    BeginTransformFeedback(GLenum),
+    EndTransformFeedback,
+    Enable(GLenum),
+    Disable(GLenum),
+    Viewport(GLint, GLint, uint, uint),
+    DepthMask(bool),
+    /// `(func, reference, mask)`
+    StencilFunc(GLenum, GLint, GLuint),
+    /// `(sfail, dpfail, dppass)`
+    StencilOp(GLenum, GLenum, GLenum),
+    StencilMask(GLuint),
+    Flush,
+    Finish,
+}
+
+pub struct Recording {
+    priv calls: @mut ~[Call],
+    priv next_name: @mut GLuint,
+    /// Attribute names in the order their locations were asked for
+    priv attribs: @mut ~[~str],
+    /// Returned for `GL_VERSION`
+    priv version: ~str,
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording::with_version("3.2.0 Recording")
+    }
+
+    /// Records calls for a context whose `GL_VERSION` string is `version`,
+    /// such as `OpenGL ES 3.0`.
+    pub fn with_version(version: &str) -> Recording {
+        Recording {
+            calls: @mut ~[],
+            next_name: @mut 1,
+            attribs: @mut ~[],
+            version: version.to_owned(),
+        }
+    }
+
+    /// The calls made so far, oldest first.
+    pub fn calls(&self) -> ~[Call] {
+        copy *self.calls
+    }
+
+    /// The calls made so far that satisfy `pred`.
+    pub fn filter(&self, pred: &fn(&Call) -> bool) -> ~[Call] {
+        self.calls.filtered(pred)
+    }
+
+    fn record(&self, call: Call) {
+        self.calls.push(call);
+    }
+
+    fn gen_name(&self) -> GLuint {
+        let name = *self.next_name;
+        *self.next_name += 1;
+        name
+    }
+}
+
+impl Gl for Recording {
+    fn gen_buffer(&self) -> GLuint {
+        let name = self.gen_name();
+        self.record(GenBuffer(name));
+        name
+    }
+
+    fn delete_buffer(&self, buffer: GLuint) { self.record(DeleteBuffer(buffer)); }
+    fn bind_buffer(&self, target: GLenum, buffer: GLuint) { self.record(BindBuffer(target, buffer)); }
+
+    fn buffer_data(&self, target: GLenum, size: uint, _: *c_void, usage: GLenum) {
+        self.record(BufferData(target, size, usage));
+    }
+
+    fn bind_buffer_base(&self, target: GLenum, index: GLuint, buffer: GLuint) {
+        self.record(BindBufferBase(target, index, buffer));
+    }
+
+    fn get_buffer_sub_data(&self, target: GLenum, offset: uint, size: uint, _: *mut c_void) {
+        self.record(GetBufferSubData(target, offset, size));
+    }
+
+    fn gen_vertex_array(&self) -> GLuint {
+        let name = self.gen_name();
+        self.record(GenVertexArray(name));
+        name
+    }
+
+    fn delete_vertex_array(&self, array: GLuint) { self.record(DeleteVertexArray(array)); }
+    fn bind_vertex_array(&self, array: GLuint) { self.record(BindVertexArray(array)); }
+    fn enable_vertex_attrib_array(&self, index: GLuint) { self.record(EnableVertexAttribArray(index)); }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, size: GLint, ty: GLenum,
+                             normalized: bool, stride: uint, offset: uint) {
+        self.record(VertexAttribPointer(index, size, ty, normalized, stride, offset));
+    }
+
+    fn create_shader(&self, ty: GLenum) -> GLuint {
+        let name = self.gen_name();
+        self.record(CreateShader(ty, name));
+        name
+    }
+
+    fn shader_source(&self, shader: GLuint, src: &str) { self.record(ShaderSource(shader, src.to_owned())); }
+    fn compile_shader(&self, shader: GLuint) { self.record(CompileShader(shader)); }
+
+    fn get_shader_iv(&self, _: GLuint, pname: GLenum) -> GLint {
+        if pname == GL_COMPILE_STATUS { GL_TRUE as GLint } else { 0 }
+    }
+
+    fn get_shader_info_log(&self, _: GLuint) -> ~str { ~"" }
+    fn delete_shader(&self, shader: GLuint) { self.record(DeleteShader(shader)); }
+
+    fn create_program(&self) -> GLuint {
+        let name = self.gen_name();
+        self.record(CreateProgram(name));
+        name
+    }
+
+    fn attach_shader(&self, program: GLuint, shader: GLuint) { self.record(AttachShader(program, shader)); }
+    fn detach_shader(&self, program: GLuint, shader: GLuint) { self.record(DetachShader(program, shader)); }
+
+    fn bind_frag_data_location(&self, program: GLuint, color: GLuint, name: &str) {
+        self.record(BindFragDataLocation(program, color, name.to_owned()));
+    }
+
+    fn transform_feedback_varyings(&self, program: GLuint, varyings: &[~str], mode: GLenum) {
+        self.record(TransformFeedbackVaryings(program, varyings.to_owned(), mode));
+    }
+
+    fn link_program(&self, program: GLuint) { self.record(LinkProgram(program)); }
+
+    fn get_program_iv(&self, _: GLuint, pname: GLenum) -> GLint {
+        if pname == GL_LINK_STATUS { GL_TRUE as GLint } else { 0 }
+    }
+
+    fn get_program_info_log(&self, _: GLuint) -> ~str { ~"" }
+    fn use_program(&self, program: GLuint) { self.record(UseProgram(program)); }
+    fn delete_program(&self, program: GLuint) { self.record(DeleteProgram(program)); }
+
+    /// Hands out locations in the order that names are first asked for.
+    fn get_attrib_location(&self, _: GLuint, name: &str) -> GLint {
+        match self.attribs.position(|a| a.as_slice() == name) {
+            Some(i) => i as GLint,
+            None => {
+                self.attribs.push(name.to_owned());
+                (self.attribs.len() - 1) as GLint
+            }
+        }
+    }
+
+    fn get_active_attrib(&self, _: GLuint, _: GLuint) -> (~str, GLenum) {
+        fail!(~"The recording backend has no active attributes")
+    }
+
+    fn get_uniform_location(&self, _: GLuint, _: &str) -> GLint { -1 }
+
+    fn get_active_uniform(&self, _: GLuint, _: GLuint) -> (~str, GLenum) {
+        fail!(~"The recording backend has no active uniforms")
+    }
+
+    fn uniform_1f(&self, location: GLint, x: GLfloat) { self.record(UniformFloats(location, ~[x])); }
+    fn uniform_1i(&self, location: GLint, x: GLint) { self.record(UniformInt(location, x)); }
+
+    fn uniform_2f(&self, location: GLint, x: GLfloat, y: GLfloat) {
+        self.record(UniformFloats(location, ~[x, y]));
+    }
+
+    fn uniform_3f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat) {
+        self.record(UniformFloats(location, ~[x, y, z]));
+    }
+
+    fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat) {
+        self.record(UniformFloats(location, ~[x, y, z, w]));
+    }
+
+    fn uniform_matrix_4fv(&self, location: GLint, value: &[GLfloat]) {
+        self.record(UniformFloats(location, value.to_owned()));
+    }
+
+    fn gen_texture(&self) -> GLuint {
+        let name = self.gen_name();
+        self.record(GenTexture(name));
+        name
+    }
+
+    fn delete_texture(&self, texture: GLuint) { self.record(DeleteTexture(texture)); }
+    fn bind_texture(&self, target: GLenum, texture: GLuint) { self.record(BindTexture(target, texture)); }
+    fn active_texture(&self, unit: GLenum) { self.record(ActiveTexture(unit)); }
+
+    fn tex_image_2d(&self, target: GLenum, level: GLint, internal_format: GLenum,
+                    width: uint, height: uint, format: GLenum, ty: GLenum, _: *c_void) {
+        self.record(TexImage2D(target, level, internal_format, width, height, format, ty));
+    }
+
+    fn tex_parameter_i(&self, target: GLenum, pname: GLenum, param: GLint) {
+        self.record(TexParameteri(target, pname, param));
+    }
+
+    fn tex_parameter_iv(&self, target: GLenum, pname: GLenum, params: &[GLint]) {
+        self.record(TexParameteriv(target, pname, params.to_owned()));
+    }
+
+    fn generate_mipmap(&self, target: GLenum) { self.record(GenerateMipmap(target)); }
+    fn pixel_store_i(&self, pname: GLenum, param: GLint) { self.record(PixelStorei(pname, param)); }
+
+    fn read_pixels(&self, x: GLint, y: GLint, width: uint, height: uint,
+                   _: GLenum, _: GLenum, _: *mut c_void) {
+        self.record(ReadPixels(x, y, width, height));
+    }
+
+    fn gen_framebuffer(&self) -> GLuint {
+        let name = self.gen_name();
+        self.record(GenFramebuffer(name));
+        name
+    }
+
+    fn delete_framebuffer(&self, framebuffer: GLuint) { self.record(DeleteFramebuffer(framebuffer)); }
+
+    fn bind_framebuffer(&self, target: GLenum, framebuffer: GLuint) {
+        self.record(BindFramebuffer(target, framebuffer));
+    }
+
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, tex_target: GLenum,
+                              texture: GLuint, level: GLint) {
+        self.record(FramebufferTexture2D(target, attachment, tex_target, texture, level));
+    }
+
+    fn check_framebuffer_status(&self, _: GLenum) -> GLenum { GL_FRAMEBUFFER_COMPLETE }
+
+    fn gen_renderbuffer(&self) -> GLuint {
+        let name = self.gen_name();
+        self.record(GenRenderbuffer(name));
+        name
+    }
+
+    fn delete_renderbuffer(&self, renderbuffer: GLuint) { self.record(DeleteRenderbuffer(renderbuffer)); }
+
+    fn bind_renderbuffer(&self, target: GLenum, renderbuffer: GLuint) {
+        self.record(BindRenderbuffer(target, renderbuffer));
+    }
+
+    fn renderbuffer_storage(&self, target: GLenum, internal_format: GLenum,
+                            width: uint, height: uint) {
+        self.record(RenderbufferStorage(target, internal_format, width, height));
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum,
+                                rb_target: GLenum, renderbuffer: GLuint) {
+        self.record(FramebufferRenderbuffer(target, attachment, rb_target, renderbuffer));
+    }
+
+    fn clear_color(&self, r: GLfloat, g: GLfloat, b: GLfloat, a: GLfloat) {
+        self.record(ClearColor(r, g, b, a));
+    }
+
+    fn clear(&self, mask: GLbitfield) { self.record(Clear(mask)); }
+
+    fn draw_arrays(&self, mode: GLenum, first: GLint, count: uint) {
+        self.record(DrawArrays(mode, first, count));
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: uint, ty: GLenum, offset: uint) {
+        self.record(DrawElements(mode, count, ty, offset));
+    }
+
+    fn begin_transform_feedback(&self, mode: GLenum) { self.record(BeginTransformFeedback(mode)); }
+    fn end_transform_feedback(&self) { self.record(EndTransformFeedback); }
+
+    fn enable(&self, cap: GLenum) { self.record(Enable(cap)); }
+    fn disable(&self, cap: GLenum) { self.record(Disable(cap)); }
+
+    fn viewport(&self, x: GLint, y: GLint, width: uint, height: uint) {
+        self.record(Viewport(x, y, width, height));
+    }
+
+    fn depth_mask(&self, flag: bool) { self.record(DepthMask(flag)); }
+
+    fn stencil_func(&self, func: GLenum, reference: GLint, mask: GLuint) {
+        self.record(StencilFunc(func, reference, mask));
+    }
+
+    fn stencil_op(&self, sfail: GLenum, dpfail: GLenum, dppass: GLenum) {
+        self.record(StencilOp(sfail, dpfail, dppass));
+    }
+
+    fn stencil_mask(&self, mask: GLuint) { self.record(StencilMask(mask)); }
+
+    fn flush(&self) { self.record(Flush); }
+    fn finish(&self) { self.record(Finish); }
+    fn get_error(&self) -> GLenum { GL_NO_ERROR }
+    fn get_integer_v(&self, _: GLenum) -> GLint { 0 }
+
+    fn get_string(&self, name: GLenum) -> ~str {
+        if name == GL_VERSION { copy self.version } else { ~"" }
+    }
+
+    fn get_string_i(&self, _: GLenum, _: GLuint) -> ~str { ~"" }
+}
@@ -0,0 +1,101 @@
+//! Programs that are rebuilt when their shader files are saved.
+
+use buffer::Buffer;
+use shader::{Program, ProgramBuilder};
+use vertex_array::VertexArray;
+
+/// A program built from shader files, which `reload` rebuilds in place
+/// whenever one of the files has changed.
+///
+/// `configure` is always called with the vertex array and buffer that the
+/// program draws from bound, so that attributes set up by it never end up
+/// in another object.
+pub struct WatchedProgram {
+    priv builder: ProgramBuilder,
+    /// Sets up a newly built program, such as binding it, pointing the
+    /// vertex attributes at it and setting uniforms that don't change
+    priv configure: ~fn(&Program) -> Result<(), ~str>,
+    /// The modification time of each file, including any it includes, when
+    /// the program was last built
+    priv files: ~[(Path, Option<(i64, int)>)],
+    priv program: Program,
+}
+
+impl WatchedProgram {
+    /// Builds the program and passes it to `configure`, with `vao` and
+    /// `vbo` bound.
+    pub fn new(builder: ProgramBuilder, vao: &VertexArray, vbo: &Buffer,
+               configure: ~fn(&Program) -> Result<(), ~str>) -> Result<WatchedProgram, ~str> {
+        let files = modification_times(&builder);
+        let program = match builder.build() {
+            Ok(program) => program,
+            Err(e) => return Err(e.to_str()),
+        };
+        bind_vertices(vao, vbo);
+        match configure(&program) {
+            Ok(()) => (),
+            Err(e) => return Err(e),
+        }
+
+        Ok(WatchedProgram {
+            builder: builder,
+            configure: configure,
+            files: files,
+            program: program,
+        })
+    }
+
+    /// The program as it was last built successfully.
+    pub fn program<'a>(&'a self) -> &'a Program {
+        &self.program
+    }
+
+    /// Rebuilds the program if any of its files have changed, returning
+    /// whether it was replaced. The program is configured with `vao` and
+    /// `vbo` bound, which are left bound. When the new program fails to
+    /// build or configure, the error is printed and the old program is
+    /// kept.
+    pub fn reload(&mut self, vao: &VertexArray, vbo: &Buffer) -> bool {
+        let changed = do self.files.any |&(ref path, time)| { path.get_mtime() != time };
+        if !changed { return false; }
+
+        // The includes may have changed too, so list the files again
+        self.files = modification_times(&self.builder);
+
+        bind_vertices(vao, vbo);
+        let result = match self.builder.build() {
+            Ok(program) => match (self.configure)(&program) {
+                Ok(()) => Ok(program),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e.to_str()),
+        };
+
+        match result {
+            Ok(program) => {
+                self.program = program;
+                true
+            }
+            Err(e) => {
+                io::stderr().write_line(e);
+                io::stderr().write_line("Keeping the previous program");
+
+                // The previous program is current again
+                match (self.configure)(&self.program) {
+                    Ok(()) => (),
+                    Err(e) => io::stderr().write_line(fmt!("Failed to restore the previous program: %s", e)),
+                }
+                false
+            }
+        }
+    }
+}
+
+fn bind_vertices(vao: &VertexArray, vbo: &Buffer) {
+    vao.bind();
+    vbo.bind();
+}
+
+fn modification_times(builder: &ProgramBuilder) -> ~[(Path, Option<(i64, int)>)] {
+    builder.files().map(|path| (copy *path, path.get_mtime()))
+}
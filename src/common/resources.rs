@@ -0,0 +1,120 @@
+//! Finding the images and other files that the chapters load.
+//!
+//! A resource is looked for, in order:
+//!
+//! 1. In the directory named by the `OPENGL_TUTORIALS_RESOURCES` environment
+//!    variable.
+//! 2. In `src/resources` under `CARGO_MANIFEST_DIR`, which is set by
+//!    `cargo run` and `cargo test`.
+//! 3. In `resources` or `src/resources` next to the executable or any
+//!    directory above it, which finds the checkout from `target/debug`.
+//! 4. In `resources` under the working directory.
+//! 5. In the binary itself, when built with the `embed-resources` feature.
+//!
+//! So the chapters work when started from any directory.
+
+/// Where a resource was found.
+pub enum Resource {
+    File(Path),
+    /// The contents of a resource built into the binary
+    Embedded(&'static [u8]),
+}
+
+#[cfg(feature = "embed-resources")]
+static embedded: &'static [(&'static str, &'static [u8])] = &[
+    ("sample.png", include_bin!("../resources/sample.png")),
+    ("sample2.png", include_bin!("../resources/sample2.png")),
+    ("shaders/c2_triangle.vert", include_bin!("../resources/shaders/c2_triangle.vert")),
+    ("shaders/c2_triangle.frag", include_bin!("../resources/shaders/c2_triangle.frag")),
+    ("shaders/c2_triangle_uniform.vert", include_bin!("../resources/shaders/c2_triangle_uniform.vert")),
+    ("shaders/c2_triangle_uniform.frag", include_bin!("../resources/shaders/c2_triangle_uniform.frag")),
+    ("shaders/c2_color_triangle.vert", include_bin!("../resources/shaders/c2_color_triangle.vert")),
+    ("shaders/c2_color_triangle.frag", include_bin!("../resources/shaders/c2_color_triangle.frag")),
+    ("shaders/c2_triangle_elements.vert", include_bin!("../resources/shaders/c2_triangle_elements.vert")),
+    ("shaders/c2_triangle_elements.frag", include_bin!("../resources/shaders/c2_triangle_elements.frag")),
+    ("shaders/attributes.glsl", include_bin!("../resources/shaders/attributes.glsl")),
+    ("shaders/textured.vert", include_bin!("../resources/shaders/textured.vert")),
+    ("shaders/textured.frag", include_bin!("../resources/shaders/textured.frag")),
+    ("shaders/post.vert", include_bin!("../resources/shaders/post.vert")),
+    ("shaders/post.frag", include_bin!("../resources/shaders/post.frag")),
+    ("shaders/c7_geometry_shader.vert", include_bin!("../resources/shaders/c7_geometry_shader.vert")),
+    ("shaders/c7_geometry_shader.geom", include_bin!("../resources/shaders/c7_geometry_shader.geom")),
+    ("shaders/c7_geometry_shader.frag", include_bin!("../resources/shaders/c7_geometry_shader.frag")),
+    ("shaders/c8_transform_feedback.vert", include_bin!("../resources/shaders/c8_transform_feedback.vert")),
+];
+
+#[cfg(not(feature = "embed-resources"))]
+static embedded: &'static [(&'static str, &'static [u8])] = &[];
+
+/// The directory that `resources` and `test` are in, for the tests.
+pub fn root() -> Path {
+    match os::getenv("CARGO_MANIFEST_DIR") {
+        Some(dir) => Path(dir).push("src"),
+        None => os::getcwd(),
+    }
+}
+
+/// The directories searched for resources, in order.
+pub fn search_dirs() -> ~[Path] {
+    let mut dirs = ~[];
+
+    match os::getenv("OPENGL_TUTORIALS_RESOURCES") {
+        Some(dir) => dirs.push(Path(dir)),
+        None => (),
+    }
+    match os::getenv("CARGO_MANIFEST_DIR") {
+        Some(dir) => dirs.push(Path(dir).push_many([~"src", ~"resources"])),
+        None => (),
+    }
+    match os::self_exe_path() {
+        Some(exe_dir) => {
+            let mut dir = exe_dir;
+            loop {
+                dirs.push(dir.push("resources"));
+                dirs.push(dir.push_many([~"src", ~"resources"]));
+                if dir.components.is_empty() { break; }
+                dir = dir.pop();
+            }
+        }
+        None => (),
+    }
+    dirs.push(os::getcwd().push("resources"));
+
+    dirs
+}
+
+/// Finds the file called `name` in the first of the `search_dirs` that has
+/// it.
+pub fn path(name: &str) -> Option<Path> {
+    for search_dirs().each |dir| {
+        let path = dir.push(name);
+        if os::path_exists(&path) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Finds the resource called `name`, falling back to the copy built into
+/// the binary.
+pub fn find(name: &str) -> Option<Resource> {
+    match path(name) {
+        Some(path) => return Some(File(path)),
+        None => (),
+    }
+    for embedded.each |&(embedded_name, bytes)| {
+        if embedded_name == name {
+            return Some(Embedded(bytes));
+        }
+    }
+    None
+}
+
+/// Reads the resource called `name` as text.
+pub fn read_str(name: &str) -> Result<~str, ~str> {
+    match find(name) {
+        Some(File(path)) => io::read_whole_file_str(&path),
+        Some(Embedded(bytes)) => Ok(str::from_bytes(bytes)),
+        None => Err(~"not found in any resource directory"),
+    }
+}
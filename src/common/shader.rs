@@ -0,0 +1,519 @@
+//! Shader compilation and program linking with diagnostics.
+
+use core::hashmap::{HashMap, HashSet};
+use debug;
+use gl;
+use glcore::*;
+use glsl;
+use resources;
+use uniform::{ActiveUniform, UniformValue, active_uniforms, glsl_type_name};
+
+/// A programmable stage of the pipeline.
+#[deriving(Eq)]
+pub enum Stage {
+    Vertex,
+    /// Runs between the vertex and fragment stages, turning each primitive
+    /// into any number of new ones
+    Geometry,
+    Fragment,
+}
+
+impl Stage {
+    fn to_glenum(&self) -> GLenum {
+        match *self {
+            Vertex   => GL_VERTEX_SHADER,
+            Geometry => GL_GEOMETRY_SHADER,
+            Fragment => GL_FRAGMENT_SHADER,
+        }
+    }
+}
+
+impl ToStr for Stage {
+    fn to_str(&self) -> ~str {
+        match *self {
+            Vertex   => ~"vertex shader",
+            Geometry => ~"geometry shader",
+            Fragment => ~"fragment shader",
+        }
+    }
+}
+
+/// A single message from a shader info log.
+pub struct Diagnostic {
+    /// The line in the shader source the message refers to, if the driver
+    /// reported one
+    line: Option<uint>,
+    /// The included file that the line is in, or `None` for the stage's
+    /// own source
+    file: Option<~str>,
+    message: ~str,
+    /// The text of the offending source line
+    source_line: Option<~str>,
+}
+
+impl ToStr for Diagnostic {
+    fn to_str(&self) -> ~str {
+        let location = match (self.line, &self.file) {
+            (Some(line), &Some(ref file)) => fmt!("%s line %u: ", *file, line),
+            (Some(line), &None) => fmt!("line %u: ", line),
+            (None, _) => ~"",
+        };
+        match self.source_line {
+            Some(ref text) => fmt!("%s%s\n    | %s", location, self.message, *text),
+            None => location + self.message,
+        }
+    }
+}
+
+pub enum ShaderError {
+    /// The source of a stage could not be read, as `(name, reason)`
+    SourceError(~str, ~str),
+    /// A stage failed to compile
+    CompileError(Stage, ~[Diagnostic]),
+    /// More than one stage failed to compile, with a `CompileError` for
+    /// each
+    StageErrors(~[ShaderError]),
+    /// The program failed to link, with the contents of the program info log
+    LinkError(~str),
+}
+
+impl ToStr for ShaderError {
+    fn to_str(&self) -> ~str {
+        match *self {
+            SourceError(ref name, ref reason) => fmt!("Failed to read %s: %s", *name, *reason),
+            CompileError(stage, ref diagnostics) => {
+                let mut s = fmt!("Failed to compile %s:", stage.to_str());
+                for diagnostics.each |d| {
+                    s.push_str("\n");
+                    s.push_str(d.to_str());
+                }
+                s
+            }
+            StageErrors(ref errors) => str::connect(errors.map(|e| e.to_str()), "\n"),
+            LinkError(ref log) => fmt!("Failed to link program:\n%s", *log),
+        }
+    }
+}
+
+/// A compiled shader object, deleted when dropped.
+pub struct Shader {
+    id: GLuint,
+    stage: Stage,
+}
+
+impl Shader {
+    /// Compiles `src` as a shader of the given stage, returning the info log
+    /// if compilation fails.
+    pub fn compile(stage: Stage, src: &str) -> Result<Shader, ShaderError> {
+        Shader::compile_sources(stage, src, [src.to_owned()], [])
+    }
+
+    /// Compiles `src`, which was put together from `sources` by the
+    /// preprocessor, so that diagnostics point into the file each line came
+    /// from. See `glsl::Preprocessed`.
+    pub fn compile_preprocessed(stage: Stage, src: &str,
+                                processed: &glsl::Preprocessed) -> Result<Shader, ShaderError> {
+        Shader::compile_sources(stage, src, processed.sources, processed.includes)
+    }
+
+    /// Compiles `src`, whose `#line` directives number the lines of
+    /// `sources`. The first source is the stage's own, and `includes` names
+    /// the rest.
+    fn compile_sources(stage: Stage, src: &str, sources: &[~str],
+                       includes: &[~str]) -> Result<Shader, ShaderError> {
+        let gl = gl::current();
+        let shader = Shader { id: check!(gl.create_shader(stage.to_glenum())), stage: stage };
+        check!(gl.shader_source(shader.id, src));
+        check!(gl.compile_shader(shader.id));
+
+        if check!(gl.get_shader_iv(shader.id, GL_COMPILE_STATUS)) == GL_TRUE as GLint {
+            Ok(shader)
+        } else {
+            let log = check!(gl.get_shader_info_log(shader.id));
+            Err(CompileError(stage, parse_log(log, sources, includes)))
+        }
+    }
+}
+
+impl Drop for Shader {
+    fn finalize(&self) {
+        let gl = gl::current();
+        check!(gl.delete_shader(self.id));
+    }
+}
+
+/// A linked shader program, deleted when dropped.
+pub struct Program {
+    id: GLuint,
+    /// The active uniforms, looked up once after linking
+    priv uniforms: HashMap<~str, ActiveUniform>,
+    /// Uniform names that have already been warned about
+    priv missing: @mut HashSet<~str>,
+}
+
+impl Program {
+    /// Links the shaders into a program. `frag_data` binds fragment shader
+    /// outputs to color numbers before linking, and `varyings` are the
+    /// outputs that transform feedback captures, interleaved in one buffer.
+    pub fn link(shaders: &[&Shader], frag_data: &[(GLuint, ~str)],
+                varyings: &[~str]) -> Result<Program, ShaderError> {
+        let gl = gl::current();
+        let mut program = Program {
+            id: check!(gl.create_program()),
+            uniforms: HashMap::new(),
+            missing: @mut HashSet::new(),
+        };
+        for shaders.each |shader| {
+            check!(gl.attach_shader(program.id, shader.id));
+        }
+        for frag_data.each |&(color, ref name)| {
+            check!(gl.bind_frag_data_location(program.id, color, *name));
+        }
+        if !varyings.is_empty() {
+            check!(gl.transform_feedback_varyings(program.id, varyings, GL_INTERLEAVED_ATTRIBS));
+        }
+        check!(gl.link_program(program.id));
+
+        // Detach the shaders so that they are deleted as soon as they drop
+        for shaders.each |shader| {
+            check!(gl.detach_shader(program.id, shader.id));
+        }
+
+        if check!(gl.get_program_iv(program.id, GL_LINK_STATUS)) == GL_TRUE as GLint {
+            program.uniforms = active_uniforms(program.id);
+            Ok(program)
+        } else {
+            Err(LinkError(check!(gl.get_program_info_log(program.id))))
+        }
+    }
+
+    /// Makes this the current program.
+    pub fn bind(&self) {
+        let gl = gl::current();
+        check!(gl.use_program(self.id));
+    }
+
+    /// Assigns a value to a uniform of this program, which must be current.
+    ///
+    /// Fails if the GLSL type of the uniform can't hold the value. Setting a
+    /// uniform that doesn't exist, or that the compiler optimized out, prints
+    /// a warning the first time and is otherwise ignored.
+    pub fn set_uniform<T: UniformValue>(&self, name: &str, value: T) {
+        match self.uniforms.find_equiv(&name) {
+            Some(uniform) => {
+                if !value.matches(uniform.ty) {
+                    fail!(fmt!("The uniform `%s` is a `%s`, which can't hold the value it was set to",
+                               name, glsl_type_name(uniform.ty)));
+                }
+                value.set(gl::current(), uniform.location);
+            }
+            None => {
+                if !self.missing.contains_equiv(&name) {
+                    io::stderr().write_line(fmt!(
+                        "Warning: the uniform `%s` is not active in program %u, it may have been optimized out",
+                        name, self.id as uint));
+                    self.missing.insert(name.to_owned());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Program {
+    fn finalize(&self) {
+        let gl = gl::current();
+        check!(gl.delete_program(self.id));
+    }
+}
+
+/// Where the source of a stage comes from.
+enum Source {
+    Inline(~str),
+    /// The name of a file found by `resources::find`
+    Resource(~str),
+}
+
+/// Collects the stages of a program and builds them in one go.
+pub struct ProgramBuilder {
+    priv stages: ~[(Stage, Source)],
+    /// Inserted into every stage as `#define` lines, see the `glsl` module
+    priv defines: ~[(~str, ~str)],
+    priv frag_data: ~[(GLuint, ~str)],
+    priv varyings: ~[~str],
+}
+
+impl ProgramBuilder {
+    pub fn new() -> ProgramBuilder {
+        ProgramBuilder { stages: ~[], defines: ~[], frag_data: ~[], varyings: ~[] }
+    }
+
+    pub fn vertex(self, src: &str) -> ProgramBuilder {
+        self.stage(Vertex, src)
+    }
+
+    pub fn geometry(self, src: &str) -> ProgramBuilder {
+        self.stage(Geometry, src)
+    }
+
+    pub fn fragment(self, src: &str) -> ProgramBuilder {
+        self.stage(Fragment, src)
+    }
+
+    pub fn stage(self, stage: Stage, src: &str) -> ProgramBuilder {
+        let mut builder = self;
+        builder.stages.push((stage, Inline(src.to_owned())));
+        builder
+    }
+
+    /// Reads the vertex shader from a resource such as
+    /// `shaders/c2_triangle.vert`.
+    pub fn vertex_file(self, name: &str) -> ProgramBuilder {
+        self.stage_file(Vertex, name)
+    }
+
+    /// Reads the geometry shader from a resource.
+    pub fn geometry_file(self, name: &str) -> ProgramBuilder {
+        self.stage_file(Geometry, name)
+    }
+
+    /// Reads the fragment shader from a resource.
+    pub fn fragment_file(self, name: &str) -> ProgramBuilder {
+        self.stage_file(Fragment, name)
+    }
+
+    /// Reads a stage from a resource when the program is built, so that
+    /// rebuilding picks up changes to the file.
+    pub fn stage_file(self, stage: Stage, name: &str) -> ProgramBuilder {
+        let mut builder = self;
+        builder.stages.push((stage, Resource(name.to_owned())));
+        builder
+    }
+
+    /// Defines the macro `name` as `value` in every stage, so that `#ifdef`
+    /// can pick out the parts of a shared file this program needs.
+    pub fn define(self, name: &str, value: &str) -> ProgramBuilder {
+        let mut builder = self;
+        builder.defines.push((name.to_owned(), value.to_owned()));
+        builder
+    }
+
+    /// Binds the fragment shader output `name` to the color number `color`,
+    /// with `glBindFragDataLocation` or a `layout(location)` qualifier
+    /// depending on the context.
+    pub fn frag_data_location(self, color: GLuint, name: &str) -> ProgramBuilder {
+        let mut builder = self;
+        builder.frag_data.push((color, name.to_owned()));
+        builder
+    }
+
+    /// Captures the outputs `names` of the last stage before rasterization
+    /// with transform feedback, in that order.
+    pub fn transform_feedback_varyings(self, names: &[&str]) -> ProgramBuilder {
+        let mut builder = self;
+        builder.varyings.push_all(names.map(|name| name.to_owned()));
+        builder
+    }
+
+    /// The files that stages and their includes are read from, for watching
+    /// for changes. Stages built into the binary have none.
+    pub fn files(&self) -> ~[Path] {
+        let mut names = ~[];
+        for self.stages.each |&(_, ref source)| {
+            match *source {
+                Resource(ref name) => names.push(copy *name),
+                Inline(_) => (),
+            }
+            // A stage that fails to preprocess is reported by `build`
+            match self.preprocess(source) {
+                Ok(processed) => names.push_all(processed.includes),
+                Err(_) => (),
+            }
+        }
+
+        let mut files = ~[];
+        for names.each |name| {
+            match resources::path(*name) {
+                Some(path) => if !files.contains(&path) { files.push(path) },
+                None => (),
+            }
+        }
+        files
+    }
+
+    /// Compiles every stage for the GLSL dialect of the current context and
+    /// links them. Every stage that fails to compile is reported, each with
+    /// the lines of its source that the driver complained about.
+    pub fn build(&self) -> Result<Program, ShaderError> {
+        let target = glsl::Target::current();
+        let mut shaders = ~[];
+        let mut errors = ~[];
+        for self.stages.each |&(stage, ref source)| {
+            let processed = match self.preprocess(source) {
+                Ok(processed) => processed,
+                Err(e) => return Err(e),
+            };
+            let outputs = if stage == Fragment { copy self.frag_data } else { ~[] };
+            let src = glsl::retarget(processed.source, target, outputs);
+            match Shader::compile_preprocessed(stage, src, &processed) {
+                Ok(shader) => shaders.push(shader),
+                Err(e) => errors.push(e),
+            }
+        }
+        match errors.len() {
+            0 => (),
+            1 => return Err(errors.pop()),
+            _ => return Err(StageErrors(errors)),
+        }
+
+        // Outputs with a location in the source need no binding, which GLES
+        // couldn't do anyway
+        let frag_data = if target.has_output_locations() { ~[] } else { copy self.frag_data };
+        Program::link(shaders.map(|s| s), frag_data, self.varyings)
+    }
+
+    /// Reads a stage and runs the preprocessor over it.
+    fn preprocess(&self, source: &Source) -> Result<glsl::Preprocessed, ShaderError> {
+        let (name, src) = match *source {
+            Inline(ref src) => (~"inline source", copy *src),
+            Resource(ref name) => match resources::read_str(*name) {
+                Ok(src) => (copy *name, src),
+                Err(e) => return Err(SourceError(copy *name, e)),
+            },
+        };
+        match glsl::preprocess(src, self.defines) {
+            Ok(processed) => Ok(processed),
+            Err(e) => Err(SourceError(name, e)),
+        }
+    }
+
+    /// Describes everything the program is built from, to tell permutations
+    /// apart in a `ProgramCache`.
+    fn key(&self) -> ~str {
+        let mut key = ~"";
+        for self.stages.each |&(stage, ref source)| {
+            key.push_str(match *source {
+                Inline(ref src) => fmt!("%s inline %s;", stage.to_str(), *src),
+                Resource(ref name) => fmt!("%s file %s;", stage.to_str(), *name),
+            });
+        }
+        for self.defines.each |&(ref name, ref value)| {
+            key.push_str(fmt!("define %s %s;", *name, *value));
+        }
+        for self.frag_data.each |&(color, ref name)| {
+            key.push_str(fmt!("frag data %u %s;", color as uint, *name));
+        }
+        for self.varyings.each |name| {
+            key.push_str(fmt!("varying %s;", *name));
+        }
+        key
+    }
+}
+
+/// Programs that have been built, so that each permutation of a shared
+/// shader is only compiled once. The programs are deleted when the cache
+/// and every copy of them are dropped.
+pub struct ProgramCache {
+    priv programs: HashMap<~str, @Program>,
+}
+
+impl ProgramCache {
+    pub fn new() -> ProgramCache {
+        ProgramCache { programs: HashMap::new() }
+    }
+
+    /// The program that `builder` describes, built the first time it is
+    /// asked for.
+    pub fn get(&mut self, builder: &ProgramBuilder) -> Result<@Program, ShaderError> {
+        let key = builder.key();
+        match self.programs.find(&key) {
+            Some(&program) => return Ok(program),
+            None => (),
+        }
+        match builder.build() {
+            Ok(program) => {
+                let program = @program;
+                self.programs.insert(key, program);
+                Ok(program)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Forgets every program, so that they are rebuilt from the current
+    /// files.
+    pub fn clear(&mut self) {
+        self.programs.clear();
+    }
+}
+
+/// Splits an info log into diagnostics, attaching the offending line to
+/// each one whose location could be found. `sources` holds the text of each
+/// source string, and `includes` the names of all but the first.
+fn parse_log(log: &str, sources: &[~str], includes: &[~str]) -> ~[Diagnostic] {
+    let mut diagnostics = ~[];
+    for str::each_line(log) |message| {
+        if message.trim().is_empty() { loop; }
+
+        let location = parse_location(message);
+        let (file, source_line) = match location {
+            Some((string, line)) if string < sources.len() => {
+                let file = if string == 0 { None } else { Some(copy includes[string - 1]) };
+                (file, nth_line(sources[string], line))
+            }
+            _ => (None, None),
+        };
+        diagnostics.push(Diagnostic {
+            line: location.map(|&(_, line)| line),
+            file: file,
+            message: message.trim().to_owned(),
+            source_line: source_line,
+        });
+    }
+    diagnostics
+}
+
+/// The trimmed text of line `n` of `src`, counting from 1.
+fn nth_line(src: &str, n: uint) -> Option<~str> {
+    let mut i = 0;
+    for str::each_line(src) |line| {
+        i += 1;
+        if i == n { return Some(line.trim().to_owned()); }
+    }
+    None
+}
+
+/// Finds the source string and line number in an info log message. Drivers
+/// disagree on the format, so this accepts the common ones:
+///
+/// - Mesa: `0:3(10): error: ...`
+/// - NVIDIA: `0(3) : error C0000: ...`
+/// - AMD and Apple: `ERROR: 0:3: ...`
+fn parse_location(message: &str) -> Option<(uint, uint)> {
+    let mut message = message.trim();
+    for ["ERROR: ", "WARNING: "].each |prefix| {
+        if message.starts_with(*prefix) {
+            message = message.slice(prefix.len(), message.len());
+        }
+    }
+
+    // The source string index, followed by `:` or `(`
+    let i = skip_digits(message, 0);
+    if i == 0 || i >= message.len() { return None; }
+    if message[i] != ':' as u8 && message[i] != '(' as u8 { return None; }
+
+    // The line number itself
+    let j = skip_digits(message, i + 1);
+    if j == i + 1 { return None; }
+    match (uint::from_str(message.slice(0, i)), uint::from_str(message.slice(i + 1, j))) {
+        (Some(string), Some(line)) => Some((string, line)),
+        _ => None,
+    }
+}
+
+fn skip_digits(s: &str, start: uint) -> uint {
+    let mut i = start;
+    while i < s.len() && char::is_digit(s[i] as char) {
+        i += 1;
+    }
+    i
+}
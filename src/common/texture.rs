@@ -0,0 +1,231 @@
+//! Texture objects and image loading.
+
+use debug;
+use gl;
+use glcore::*;
+use resources;
+use resources::{File, Embedded};
+use stb_image::image;
+use stb_image::image::{LoadResult, ImageU8, ImageF32};
+
+/// How texture coordinates outside `[0, 1]` are handled.
+#[deriving(Eq)]
+pub enum Wrap {
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat,
+}
+
+impl Wrap {
+    fn to_glint(&self) -> GLint {
+        (match *self {
+            ClampToEdge    => GL_CLAMP_TO_EDGE,
+            Repeat         => GL_REPEAT,
+            MirroredRepeat => GL_MIRRORED_REPEAT,
+        }) as GLint
+    }
+}
+
+#[deriving(Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+impl Filter {
+    fn to_glint(&self) -> GLint {
+        (match *self {
+            Nearest => GL_NEAREST,
+            Linear  => GL_LINEAR,
+        }) as GLint
+    }
+
+    /// The minification filter to use when sampling between mipmaps.
+    fn to_mipmap_glint(&self) -> GLint {
+        (match *self {
+            Nearest => GL_NEAREST_MIPMAP_NEAREST,
+            Linear  => GL_LINEAR_MIPMAP_LINEAR,
+        }) as GLint
+    }
+}
+
+/// How a texture is sampled.
+pub struct Options {
+    wrap: Wrap,
+    min_filter: Filter,
+    mag_filter: Filter,
+    /// Generate mipmaps after uploading the image
+    mipmaps: bool,
+}
+
+impl Options {
+    /// Clamped to the edges with linear filtering and no mipmaps.
+    pub fn default() -> Options {
+        Options {
+            wrap: ClampToEdge,
+            min_filter: Linear,
+            mag_filter: Linear,
+            mipmaps: false,
+        }
+    }
+}
+
+pub enum LoadError {
+    /// There is no file at the path
+    NotFound(~str),
+    /// The resource is not in any of the `resources::search_dirs`
+    ResourceNotFound(~str),
+    /// stb_image could not decode the file
+    DecodeError(~str),
+    /// The image is a floating point (HDR) image
+    NotEightBit(~str),
+    /// The image has a number of channels other than 1 to 4, as
+    /// `(path, channels)`
+    UnsupportedDepth(~str, uint),
+}
+
+impl ToStr for LoadError {
+    fn to_str(&self) -> ~str {
+        match *self {
+            NotFound(ref path) => fmt!("Failed to load %s: file not found", *path),
+            ResourceNotFound(ref name) => {
+                fmt!("Failed to load %s: not found in any resource directory, \
+                      set OPENGL_TUTORIALS_RESOURCES to the directory it is in", *name)
+            }
+            DecodeError(ref path) => fmt!("Failed to load %s: the image could not be decoded", *path),
+            NotEightBit(ref path) => fmt!("Failed to load %s: only 8-bit images are supported", *path),
+            UnsupportedDepth(ref path, depth) => {
+                fmt!("Failed to load %s: unsupported number of channels (%u)", *path, depth)
+            }
+        }
+    }
+}
+
+/// An owned 2D texture object, deleted when dropped.
+pub struct Texture {
+    id: GLuint,
+}
+
+impl Texture {
+    pub fn new() -> Texture {
+        Texture { id: check!(gl::current().gen_texture()) }
+    }
+
+    /// Creates an RGBA texture of `width` by `height` pixels with undefined
+    /// contents, for drawing into with a framebuffer. It is left bound to
+    /// the active texture unit.
+    pub fn empty(width: uint, height: uint, options: &Options) -> Texture {
+        let gl = gl::current();
+        let texture = Texture::new();
+        texture.bind();
+        check!(gl.tex_image_2d(GL_TEXTURE_2D, 0, GL_RGBA, width, height,
+                               GL_RGBA, GL_UNSIGNED_BYTE, ptr::null()));
+        texture.set_options(options);
+        texture
+    }
+
+    /// Loads an image file into a new texture, which is left bound to the
+    /// active texture unit. Grayscale images are sampled as gray in every
+    /// color channel, and images without alpha are opaque.
+    pub fn load(path: &str, options: &Options) -> Result<Texture, LoadError> {
+        if !os::path_exists(&Path(path)) {
+            return Err(NotFound(path.to_owned()));
+        }
+        Texture::from_image(path, image::load(path.to_owned()), options)
+    }
+
+    /// Loads an image found by `resources::find`, like `load`.
+    pub fn load_resource(name: &str, options: &Options) -> Result<Texture, LoadError> {
+        match resources::find(name) {
+            Some(File(path)) => Texture::load(path.to_str(), options),
+            Some(Embedded(bytes)) => {
+                Texture::from_image(name, image::load_from_memory(bytes), options)
+            }
+            None => Err(ResourceNotFound(name.to_owned())),
+        }
+    }
+
+    /// Uploads a decoded image, which was loaded from `path`.
+    fn from_image(path: &str, result: LoadResult,
+                  options: &Options) -> Result<Texture, LoadError> {
+        match result {
+            ImageU8(image) => {
+                // Rows of 3 channel images are not always 4 byte aligned
+                let gl = gl::current();
+                check!(gl.pixel_store_i(GL_UNPACK_ALIGNMENT, 1));
+
+                let texture = Texture::new();
+                texture.bind();
+                match image.depth {
+                    // Texture swizzles need GL 3.3, so grayscale images are
+                    // spread over the color channels before uploading
+                    1 => upload(GL_RGB, image.width, image.height, expand_gray(image.data, 1)),
+                    2 => upload(GL_RGBA, image.width, image.height, expand_gray(image.data, 2)),
+                    3 => upload(GL_RGB, image.width, image.height, image.data),
+                    4 => upload(GL_RGBA, image.width, image.height, image.data),
+                    depth => return Err(UnsupportedDepth(path.to_owned(), depth)),
+                }
+                texture.set_options(options);
+                Ok(texture)
+            }
+            ImageF32(_) => Err(NotEightBit(path.to_owned())),
+            _ => Err(DecodeError(path.to_owned())),
+        }
+    }
+
+    pub fn bind(&self) {
+        check!(gl::current().bind_texture(GL_TEXTURE_2D, self.id));
+    }
+
+    /// Binds the texture to a texture unit, counting from zero.
+    pub fn bind_to_unit(&self, unit: uint) {
+        check!(gl::current().active_texture(GL_TEXTURE0 + unit as GLenum));
+        self.bind();
+    }
+
+    /// Sets the wrapping and filtering of the texture, which must be bound.
+    pub fn set_options(&self, options: &Options) {
+        let gl = gl::current();
+        check!(gl.tex_parameter_i(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, options.wrap.to_glint()));
+        check!(gl.tex_parameter_i(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, options.wrap.to_glint()));
+        check!(gl.tex_parameter_i(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, options.mag_filter.to_glint()));
+
+        if options.mipmaps {
+            check!(gl.generate_mipmap(GL_TEXTURE_2D));
+            check!(gl.tex_parameter_i(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, options.min_filter.to_mipmap_glint()));
+        } else {
+            check!(gl.tex_parameter_i(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, options.min_filter.to_glint()));
+        }
+    }
+}
+
+/// Copies the pixels of an image to the bound texture.
+fn upload(format: GLenum, width: uint, height: uint, data: &[u8]) {
+    let gl = gl::current();
+    unsafe {
+        check!(gl.tex_image_2d(GL_TEXTURE_2D, 0, format, width, height,
+                               format, GL_UNSIGNED_BYTE,
+                               cast::transmute(vec::raw::to_ptr(data))));
+    }
+}
+
+/// Repeats the gray channel of each pixel of a 1 or 2 channel image as red,
+/// green and blue, keeping any alpha.
+fn expand_gray(data: &[u8], depth: uint) -> ~[u8] {
+    let pixels = data.len() / depth;
+    let mut expanded = vec::with_capacity(pixels * (depth + 2));
+    for uint::range(0, pixels) |i| {
+        let gray = data[i * depth];
+        expanded.push_all([gray, gray, gray]);
+        if depth == 2 {
+            expanded.push(data[i * depth + 1]);
+        }
+    }
+    expanded
+}
+
+impl Drop for Texture {
+    fn finalize(&self) {
+        check!(gl::current().delete_texture(self.id));
+    }
+}
@@ -0,0 +1,346 @@
+//! A GL backend that keeps track of the objects that are alive.
+//!
+//! Calls are passed on to another backend, and every buffer, vertex array,
+//! shader, program, texture, framebuffer and renderbuffer that is created is
+//! remembered until it is deleted. The chapter switcher uses it to find
+//! anything a chapter forgot to delete.
+
+use core::libc::c_void;
+use gl::Gl;
+use glcore::*;
+
+/// A GL object, by kind and name.
+#[deriving(Eq)]
+pub enum Object {
+    Buffer(GLuint),
+    VertexArray(GLuint),
+    Shader(GLuint),
+    Program(GLuint),
+    Texture(GLuint),
+    Framebuffer(GLuint),
+    Renderbuffer(GLuint),
+}
+
+impl ToStr for Object {
+    fn to_str(&self) -> ~str {
+        match *self {
+            Buffer(name)       => fmt!("buffer %u", name as uint),
+            VertexArray(name)  => fmt!("vertex array %u", name as uint),
+            Shader(name)       => fmt!("shader %u", name as uint),
+            Program(name)      => fmt!("program %u", name as uint),
+            Texture(name)      => fmt!("texture %u", name as uint),
+            Framebuffer(name)  => fmt!("framebuffer %u", name as uint),
+            Renderbuffer(name) => fmt!("renderbuffer %u", name as uint),
+        }
+    }
+}
+
+pub struct Tracking {
+    priv inner: @Gl,
+    priv live: @mut ~[Object],
+}
+
+impl Tracking {
+    /// Tracks the objects made through `inner`.
+    pub fn new(inner: @Gl) -> Tracking {
+        Tracking { inner: inner, live: @mut ~[] }
+    }
+
+    /// The objects that have been created and not yet deleted, oldest
+    /// first.
+    pub fn live(&self) -> ~[Object] {
+        copy *self.live
+    }
+
+    /// Stops tracking the objects that are alive, so that only objects
+    /// created from now on are reported by `live`.
+    pub fn clear(&self) {
+        self.live.clear();
+    }
+
+    fn forget(&self, object: Object) {
+        match self.live.position_elem(&object) {
+            Some(i) => { self.live.remove(i); }
+            None => (),
+        }
+    }
+}
+
+impl Gl for Tracking {
+    fn gen_buffer(&self) -> GLuint {
+        let name = self.inner.gen_buffer();
+        self.live.push(Buffer(name));
+        name
+    }
+
+    fn delete_buffer(&self, buffer: GLuint) {
+        self.forget(Buffer(buffer));
+        self.inner.delete_buffer(buffer);
+    }
+
+    fn bind_buffer(&self, target: GLenum, buffer: GLuint) {
+        self.inner.bind_buffer(target, buffer);
+    }
+
+    fn buffer_data(&self, target: GLenum, size: uint, data: *c_void, usage: GLenum) {
+        self.inner.buffer_data(target, size, data, usage);
+    }
+
+    fn bind_buffer_base(&self, target: GLenum, index: GLuint, buffer: GLuint) {
+        self.inner.bind_buffer_base(target, index, buffer);
+    }
+
+    fn get_buffer_sub_data(&self, target: GLenum, offset: uint, size: uint, data: *mut c_void) {
+        self.inner.get_buffer_sub_data(target, offset, size, data);
+    }
+
+    fn gen_vertex_array(&self) -> GLuint {
+        let name = self.inner.gen_vertex_array();
+        self.live.push(VertexArray(name));
+        name
+    }
+
+    fn delete_vertex_array(&self, array: GLuint) {
+        self.forget(VertexArray(array));
+        self.inner.delete_vertex_array(array);
+    }
+
+    fn bind_vertex_array(&self, array: GLuint) { self.inner.bind_vertex_array(array); }
+
+    fn enable_vertex_attrib_array(&self, index: GLuint) {
+        self.inner.enable_vertex_attrib_array(index);
+    }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, size: GLint, ty: GLenum,
+                             normalized: bool, stride: uint, offset: uint) {
+        self.inner.vertex_attrib_pointer(index, size, ty, normalized, stride, offset);
+    }
+
+    fn create_shader(&self, ty: GLenum) -> GLuint {
+        let name = self.inner.create_shader(ty);
+        self.live.push(Shader(name));
+        name
+    }
+
+    fn shader_source(&self, shader: GLuint, src: &str) { self.inner.shader_source(shader, src); }
+    fn compile_shader(&self, shader: GLuint) { self.inner.compile_shader(shader); }
+
+    fn get_shader_iv(&self, shader: GLuint, pname: GLenum) -> GLint {
+        self.inner.get_shader_iv(shader, pname)
+    }
+
+    fn get_shader_info_log(&self, shader: GLuint) -> ~str { self.inner.get_shader_info_log(shader) }
+
+    fn delete_shader(&self, shader: GLuint) {
+        self.forget(Shader(shader));
+        self.inner.delete_shader(shader);
+    }
+
+    fn create_program(&self) -> GLuint {
+        let name = self.inner.create_program();
+        self.live.push(Program(name));
+        name
+    }
+
+    fn attach_shader(&self, program: GLuint, shader: GLuint) {
+        self.inner.attach_shader(program, shader);
+    }
+
+    fn detach_shader(&self, program: GLuint, shader: GLuint) {
+        self.inner.detach_shader(program, shader);
+    }
+
+    fn bind_frag_data_location(&self, program: GLuint, color: GLuint, name: &str) {
+        self.inner.bind_frag_data_location(program, color, name);
+    }
+
+    fn transform_feedback_varyings(&self, program: GLuint, varyings: &[~str], mode: GLenum) {
+        self.inner.transform_feedback_varyings(program, varyings, mode);
+    }
+
+    fn link_program(&self, program: GLuint) { self.inner.link_program(program); }
+
+    fn get_program_iv(&self, program: GLuint, pname: GLenum) -> GLint {
+        self.inner.get_program_iv(program, pname)
+    }
+
+    fn get_program_info_log(&self, program: GLuint) -> ~str {
+        self.inner.get_program_info_log(program)
+    }
+
+    fn use_program(&self, program: GLuint) { self.inner.use_program(program); }
+
+    fn delete_program(&self, program: GLuint) {
+        self.forget(Program(program));
+        self.inner.delete_program(program);
+    }
+
+    fn get_attrib_location(&self, program: GLuint, name: &str) -> GLint {
+        self.inner.get_attrib_location(program, name)
+    }
+
+    fn get_active_attrib(&self, program: GLuint, index: GLuint) -> (~str, GLenum) {
+        self.inner.get_active_attrib(program, index)
+    }
+
+    fn get_uniform_location(&self, program: GLuint, name: &str) -> GLint {
+        self.inner.get_uniform_location(program, name)
+    }
+
+    fn get_active_uniform(&self, program: GLuint, index: GLuint) -> (~str, GLenum) {
+        self.inner.get_active_uniform(program, index)
+    }
+
+    fn uniform_1f(&self, location: GLint, x: GLfloat) { self.inner.uniform_1f(location, x); }
+    fn uniform_1i(&self, location: GLint, x: GLint) { self.inner.uniform_1i(location, x); }
+
+    fn uniform_2f(&self, location: GLint, x: GLfloat, y: GLfloat) {
+        self.inner.uniform_2f(location, x, y);
+    }
+
+    fn uniform_3f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat) {
+        self.inner.uniform_3f(location, x, y, z);
+    }
+
+    fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat) {
+        self.inner.uniform_4f(location, x, y, z, w);
+    }
+
+    fn uniform_matrix_4fv(&self, location: GLint, value: &[GLfloat]) {
+        self.inner.uniform_matrix_4fv(location, value);
+    }
+
+    fn gen_texture(&self) -> GLuint {
+        let name = self.inner.gen_texture();
+        self.live.push(Texture(name));
+        name
+    }
+
+    fn delete_texture(&self, texture: GLuint) {
+        self.forget(Texture(texture));
+        self.inner.delete_texture(texture);
+    }
+
+    fn bind_texture(&self, target: GLenum, texture: GLuint) {
+        self.inner.bind_texture(target, texture);
+    }
+
+    fn active_texture(&self, unit: GLenum) { self.inner.active_texture(unit); }
+
+    fn tex_image_2d(&self, target: GLenum, level: GLint, internal_format: GLenum,
+                    width: uint, height: uint, format: GLenum, ty: GLenum, data: *c_void) {
+        self.inner.tex_image_2d(target, level, internal_format, width, height, format, ty, data);
+    }
+
+    fn tex_parameter_i(&self, target: GLenum, pname: GLenum, param: GLint) {
+        self.inner.tex_parameter_i(target, pname, param);
+    }
+
+    fn tex_parameter_iv(&self, target: GLenum, pname: GLenum, params: &[GLint]) {
+        self.inner.tex_parameter_iv(target, pname, params);
+    }
+
+    fn generate_mipmap(&self, target: GLenum) { self.inner.generate_mipmap(target); }
+    fn pixel_store_i(&self, pname: GLenum, param: GLint) { self.inner.pixel_store_i(pname, param); }
+
+    fn read_pixels(&self, x: GLint, y: GLint, width: uint, height: uint, format: GLenum,
+                   ty: GLenum, data: *mut c_void) {
+        self.inner.read_pixels(x, y, width, height, format, ty, data);
+    }
+
+    fn gen_framebuffer(&self) -> GLuint {
+        let name = self.inner.gen_framebuffer();
+        self.live.push(Framebuffer(name));
+        name
+    }
+
+    fn delete_framebuffer(&self, framebuffer: GLuint) {
+        self.forget(Framebuffer(framebuffer));
+        self.inner.delete_framebuffer(framebuffer);
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, framebuffer: GLuint) {
+        self.inner.bind_framebuffer(target, framebuffer);
+    }
+
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, tex_target: GLenum,
+                              texture: GLuint, level: GLint) {
+        self.inner.framebuffer_texture_2d(target, attachment, tex_target, texture, level);
+    }
+
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum {
+        self.inner.check_framebuffer_status(target)
+    }
+
+    fn gen_renderbuffer(&self) -> GLuint {
+        let name = self.inner.gen_renderbuffer();
+        self.live.push(Renderbuffer(name));
+        name
+    }
+
+    fn delete_renderbuffer(&self, renderbuffer: GLuint) {
+        self.forget(Renderbuffer(renderbuffer));
+        self.inner.delete_renderbuffer(renderbuffer);
+    }
+
+    fn bind_renderbuffer(&self, target: GLenum, renderbuffer: GLuint) {
+        self.inner.bind_renderbuffer(target, renderbuffer);
+    }
+
+    fn renderbuffer_storage(&self, target: GLenum, internal_format: GLenum,
+                            width: uint, height: uint) {
+        self.inner.renderbuffer_storage(target, internal_format, width, height);
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum,
+                                rb_target: GLenum, renderbuffer: GLuint) {
+        self.inner.framebuffer_renderbuffer(target, attachment, rb_target, renderbuffer);
+    }
+
+    fn clear_color(&self, r: GLfloat, g: GLfloat, b: GLfloat, a: GLfloat) {
+        self.inner.clear_color(r, g, b, a);
+    }
+
+    fn clear(&self, mask: GLbitfield) { self.inner.clear(mask); }
+
+    fn draw_arrays(&self, mode: GLenum, first: GLint, count: uint) {
+        self.inner.draw_arrays(mode, first, count);
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: uint, ty: GLenum, offset: uint) {
+        self.inner.draw_elements(mode, count, ty, offset);
+    }
+
+    fn begin_transform_feedback(&self, mode: GLenum) { self.inner.begin_transform_feedback(mode); }
+    fn end_transform_feedback(&self) { self.inner.end_transform_feedback(); }
+
+    fn enable(&self, cap: GLenum) { self.inner.enable(cap); }
+    fn disable(&self, cap: GLenum) { self.inner.disable(cap); }
+
+    fn viewport(&self, x: GLint, y: GLint, width: uint, height: uint) {
+        self.inner.viewport(x, y, width, height);
+    }
+
+    fn depth_mask(&self, flag: bool) { self.inner.depth_mask(flag); }
+
+    fn stencil_func(&self, func: GLenum, reference: GLint, mask: GLuint) {
+        self.inner.stencil_func(func, reference, mask);
+    }
+
+    fn stencil_op(&self, sfail: GLenum, dpfail: GLenum, dppass: GLenum) {
+        self.inner.stencil_op(sfail, dpfail, dppass);
+    }
+
+    fn stencil_mask(&self, mask: GLuint) { self.inner.stencil_mask(mask); }
+
+    fn flush(&self) { self.inner.flush(); }
+    fn finish(&self) { self.inner.finish(); }
+    fn get_error(&self) -> GLenum { self.inner.get_error() }
+    fn get_integer_v(&self, pname: GLenum) -> GLint { self.inner.get_integer_v(pname) }
+
+    fn get_string(&self, name: GLenum) -> ~str { self.inner.get_string(name) }
+
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> ~str {
+        self.inner.get_string_i(name, index)
+    }
+}
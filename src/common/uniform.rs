@@ -0,0 +1,119 @@
+//! Values that can be assigned to shader uniforms.
+
+use core::hashmap::HashMap;
+use debug;
+use gl;
+use gl::Gl;
+use glcore::*;
+use lmath::mat::Mat4;
+
+/// A uniform that the linked program actually uses.
+pub struct ActiveUniform {
+    location: GLint,
+    /// The GLSL type, eg. `GL_FLOAT_VEC3`
+    ty: GLenum,
+}
+
+/// The texture unit that a sampler uniform reads from.
+pub struct Sampler(uint);
+
+/// A Rust value that can be assigned to uniforms of some GLSL types.
+pub trait UniformValue {
+    /// Whether a uniform of the GLSL type `ty` can hold this value.
+    fn matches(&self, ty: GLenum) -> bool;
+
+    /// Assigns the value to the uniform at `location` in the current
+    /// program.
+    fn set(&self, gl: @Gl, location: GLint);
+}
+
+impl UniformValue for GLfloat {
+    fn matches(&self, ty: GLenum) -> bool { ty == GL_FLOAT }
+    fn set(&self, gl: @Gl, location: GLint) { check!(gl.uniform_1f(location, *self)); }
+}
+
+impl UniformValue for GLint {
+    fn matches(&self, ty: GLenum) -> bool { ty == GL_INT || ty == GL_BOOL }
+    fn set(&self, gl: @Gl, location: GLint) { check!(gl.uniform_1i(location, *self)); }
+}
+
+impl UniformValue for [GLfloat, ..2] {
+    fn matches(&self, ty: GLenum) -> bool { ty == GL_FLOAT_VEC2 }
+    fn set(&self, gl: @Gl, location: GLint) { check!(gl.uniform_2f(location, self[0], self[1])); }
+}
+
+impl UniformValue for [GLfloat, ..3] {
+    fn matches(&self, ty: GLenum) -> bool { ty == GL_FLOAT_VEC3 }
+    fn set(&self, gl: @Gl, location: GLint) { check!(gl.uniform_3f(location, self[0], self[1], self[2])); }
+}
+
+impl UniformValue for [GLfloat, ..4] {
+    fn matches(&self, ty: GLenum) -> bool { ty == GL_FLOAT_VEC4 }
+    fn set(&self, gl: @Gl, location: GLint) { check!(gl.uniform_4f(location, self[0], self[1], self[2], self[3])); }
+}
+
+impl UniformValue for Mat4<GLfloat> {
+    fn matches(&self, ty: GLenum) -> bool { ty == GL_FLOAT_MAT4 }
+
+    fn set(&self, gl: @Gl, location: GLint) {
+        unsafe {
+            do vec::raw::buf_as_slice(self.to_ptr(), 16) |values| {
+                check!(gl.uniform_matrix_4fv(location, values));
+            }
+        }
+    }
+}
+
+impl UniformValue for Sampler {
+    fn matches(&self, ty: GLenum) -> bool {
+        match ty {
+            GL_SAMPLER_1D | GL_SAMPLER_2D | GL_SAMPLER_3D | GL_SAMPLER_CUBE |
+            GL_SAMPLER_2D_SHADOW | GL_SAMPLER_2D_RECT => true,
+            _ => false,
+        }
+    }
+
+    fn set(&self, gl: @Gl, location: GLint) {
+        let Sampler(unit) = *self;
+        check!(gl.uniform_1i(location, unit as GLint));
+    }
+}
+
+/// Looks up every active uniform in a linked program by name. Array
+/// uniforms are keyed by their name without the `[0]` suffix.
+pub fn active_uniforms(program: GLuint) -> HashMap<~str, ActiveUniform> {
+    let gl = gl::current();
+    let count = check!(gl.get_program_iv(program, GL_ACTIVE_UNIFORMS));
+
+    let mut uniforms = HashMap::new();
+    for uint::range(0, count as uint) |i| {
+        let (mut name, ty) = check!(gl.get_active_uniform(program, i as GLuint));
+        if name.ends_with("[0]") {
+            name = name.slice(0, name.len() - 3).to_owned();
+        }
+
+        // Uniforms in named blocks have no location
+        let location = check!(gl.get_uniform_location(program, name));
+        if location >= 0 {
+            uniforms.insert(name, ActiveUniform { location: location, ty: ty });
+        }
+    }
+    uniforms
+}
+
+/// The GLSL name of a type enum, for error messages.
+pub fn glsl_type_name(ty: GLenum) -> ~str {
+    match ty {
+        GL_FLOAT        => ~"float",
+        GL_FLOAT_VEC2   => ~"vec2",
+        GL_FLOAT_VEC3   => ~"vec3",
+        GL_FLOAT_VEC4   => ~"vec4",
+        GL_INT          => ~"int",
+        GL_BOOL         => ~"bool",
+        GL_FLOAT_MAT3   => ~"mat3",
+        GL_FLOAT_MAT4   => ~"mat4",
+        GL_SAMPLER_2D   => ~"sampler2D",
+        GL_SAMPLER_CUBE => ~"samplerCube",
+        _               => fmt!("type 0x%x", ty as uint),
+    }
+}
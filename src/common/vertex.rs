@@ -0,0 +1,196 @@
+//! Descriptions of interleaved vertex data.
+
+use debug;
+use gl;
+use glcore::*;
+use shader::Program;
+use uniform::glsl_type_name;
+
+/// A floating point vertex attribute within an interleaved vertex.
+pub struct Attribute {
+    /// The name of the matching `in` variable in the vertex shader
+    name: ~str,
+    /// The number of `GLfloat` components
+    components: uint,
+    /// The offset in bytes from the start of the vertex
+    offset: uint,
+}
+
+/// The types of field that can be a vertex attribute.
+pub trait AttribValue {
+    /// The number of `GLfloat` components
+    fn components(&self) -> uint;
+}
+
+impl AttribValue for GLfloat {
+    fn components(&self) -> uint { 1 }
+}
+
+impl AttribValue for [GLfloat, ..2] {
+    fn components(&self) -> uint { 2 }
+}
+
+impl AttribValue for [GLfloat, ..3] {
+    fn components(&self) -> uint { 3 }
+}
+
+impl AttribValue for [GLfloat, ..4] {
+    fn components(&self) -> uint { 4 }
+}
+
+impl Attribute {
+    /// The size of the attribute in bytes.
+    fn size(&self) -> uint {
+        self.components * sys::size_of::<GLfloat>()
+    }
+}
+
+/// The attributes making up a vertex type, in the order they are declared.
+pub struct Layout {
+    priv attributes: ~[Attribute],
+    /// The size of one vertex in bytes
+    priv stride: uint,
+    /// The address of the vertex that fields are measured from
+    priv base: uint,
+}
+
+pub enum LayoutError {
+    /// The shader has an input that the layout does not describe
+    MissingAttribute(~str),
+    /// The shader input has a different type to the attribute, as
+    /// `(name, GLSL type, attribute components)`
+    TypeMismatch(~str, ~str, uint),
+    /// The attributes don't add up to the size of the vertex, as
+    /// `(attribute bytes, vertex bytes)`
+    SizeMismatch(uint, uint),
+    /// The field given for the attribute is not part of the vertex
+    OutsideVertex(~str),
+}
+
+impl ToStr for LayoutError {
+    fn to_str(&self) -> ~str {
+        match *self {
+            MissingAttribute(ref name) => {
+                fmt!("The vertex shader input `%s` is not in the vertex layout", *name)
+            }
+            TypeMismatch(ref name, ref glsl_type, components) => {
+                fmt!("The vertex shader input `%s` is a `%s`, but the layout has %u components",
+                     *name, *glsl_type, components)
+            }
+            SizeMismatch(attribute_size, vertex_size) => {
+                fmt!("The attributes in the vertex layout take up %u bytes, but the vertex is %u bytes",
+                     attribute_size, vertex_size)
+            }
+            OutsideVertex(ref name) => {
+                fmt!("The field given for the attribute `%s` is not part of the vertex", *name)
+            }
+        }
+    }
+}
+
+impl Layout {
+    /// Starts an empty layout for the type of `vertex`, which any vertex of
+    /// that type will do for.
+    pub fn new<T>(vertex: &T) -> Layout {
+        Layout {
+            attributes: ~[],
+            stride: sys::size_of::<T>(),
+            base: ptr::to_unsafe_ptr(vertex) as uint,
+        }
+    }
+
+    /// Appends the attribute stored in `field` of the vertex passed to
+    /// `new`, taking its offset and number of components from the field
+    /// itself:
+    ///
+    /// ~~~
+    /// let v = &vertices[0];
+    /// Layout::new(v).attrib("position", &v.position).attrib("color", &v.color)
+    /// ~~~
+    pub fn attrib<F: AttribValue>(self, name: &str, field: &F) -> Layout {
+        let mut layout = self;
+        // A field of some other value wraps around to a huge offset, which
+        // `bind` reports
+        let offset = ptr::to_unsafe_ptr(field) as uint - layout.base;
+        layout.attributes.push(Attribute {
+            name: name.to_owned(),
+            components: field.components(),
+            offset: offset,
+        });
+        layout
+    }
+
+    /// The combined size of the attributes in bytes.
+    pub fn size(&self) -> uint {
+        self.attributes.foldl(0, |&size, a| size + a.size())
+    }
+
+    /// The size of one vertex in bytes.
+    pub fn stride(&self) -> uint {
+        self.stride
+    }
+
+    /// Checks the layout against the inputs of the vertex shader, then
+    /// points each attribute at the currently bound array buffer.
+    /// Attributes that the shader does not use are skipped.
+    pub fn bind(&self, program: &Program) -> Result<(), LayoutError> {
+        for self.attributes.each |a| {
+            if a.offset > self.stride || a.size() > self.stride - a.offset {
+                return Err(OutsideVertex(copy a.name));
+            }
+        }
+        if self.size() != self.stride {
+            return Err(SizeMismatch(self.size(), self.stride));
+        }
+
+        for active_attributes(program).each |&(ref name, ty)| {
+            match self.attributes.find(|a| a.name == *name) {
+                None => return Err(MissingAttribute(copy *name)),
+                Some(a) => {
+                    if component_count(ty) != Some(a.components) {
+                        return Err(TypeMismatch(copy *name, glsl_type_name(ty), a.components));
+                    }
+                }
+            }
+        }
+
+        let gl = gl::current();
+        for self.attributes.each |a| {
+            let location = check!(gl.get_attrib_location(program.id, a.name));
+            if location < 0 { loop; }
+
+            check!(gl.enable_vertex_attrib_array(location as GLuint));
+            check!(gl.vertex_attrib_pointer(location as GLuint, a.components as GLint,
+                                            GL_FLOAT, false, self.stride, a.offset));
+        }
+
+        Ok(())
+    }
+}
+
+/// The names and types of the attributes that the program actually uses.
+fn active_attributes(program: &Program) -> ~[(~str, GLenum)] {
+    let gl = gl::current();
+    let count = check!(gl.get_program_iv(program.id, GL_ACTIVE_ATTRIBUTES));
+
+    let mut attributes = ~[];
+    for uint::range(0, count as uint) |i| {
+        let (name, ty) = check!(gl.get_active_attrib(program.id, i as GLuint));
+
+        // Built in inputs such as `gl_VertexID` are never in a layout
+        if !name.starts_with("gl_") {
+            attributes.push((name, ty));
+        }
+    }
+    attributes
+}
+
+fn component_count(ty: GLenum) -> Option<uint> {
+    match ty {
+        GL_FLOAT      => Some(1),
+        GL_FLOAT_VEC2 => Some(2),
+        GL_FLOAT_VEC3 => Some(3),
+        GL_FLOAT_VEC4 => Some(4),
+        _             => None,
+    }
+}
@@ -0,0 +1,29 @@
+//! Vertex array objects.
+
+use debug;
+use gl;
+use glcore::*;
+
+/// An owned vertex array object, deleted when dropped.
+pub struct VertexArray {
+    id: GLuint,
+}
+
+impl VertexArray {
+    pub fn new() -> VertexArray {
+        let gl = gl::current();
+        VertexArray { id: check!(gl.gen_vertex_array()) }
+    }
+
+    pub fn bind(&self) {
+        let gl = gl::current();
+        check!(gl.bind_vertex_array(self.id));
+    }
+}
+
+impl Drop for VertexArray {
+    fn finalize(&self) {
+        let gl = gl::current();
+        check!(gl.delete_vertex_array(self.id));
+    }
+}
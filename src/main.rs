@@ -0,0 +1,171 @@
+//! Runs any of the chapters by name.
+//!
+//! ~~~
+//! opengl-tutorials [--size WIDTHxHEIGHT] [--no-vsync] [--samples N]
+//!                  [--fullscreen] [CHAPTER]
+//! ~~~
+//!
+//! Once the window is open, the left and right arrow keys step through the
+//! chapters, and the number keys switch straight to one of the first ten.
+
+extern mod std;
+extern mod common;
+extern mod glcore;
+extern mod lmath;
+extern mod numeric;
+
+use common::chapter;
+use common::chapter::Running;
+use common::context;
+use common::context::Surface;
+use std::getopts::*;
+
+#[path = "c2_triangle.rs"]
+mod c2_triangle;
+#[path = "c2_triangle_uniform.rs"]
+mod c2_triangle_uniform;
+#[path = "c2_color_triangle.rs"]
+mod c2_color_triangle;
+#[path = "c2_triangle_elements.rs"]
+mod c2_triangle_elements;
+#[path = "c3_basic.rs"]
+mod c3_basic;
+#[path = "c3_multitexture.rs"]
+mod c3_multitexture;
+#[path = "c4_transformation.rs"]
+mod c4_transformation;
+#[path = "c5_depth_stencil.rs"]
+mod c5_depth_stencil;
+#[path = "c6_framebuffers.rs"]
+mod c6_framebuffers;
+#[path = "c7_geometry_shader.rs"]
+mod c7_geometry_shader;
+#[path = "c8_transform_feedback.rs"]
+mod c8_transform_feedback;
+
+static chapters: [&'static str, ..11] = [
+    "c2_triangle",
+    "c2_triangle_uniform",
+    "c2_color_triangle",
+    "c2_triangle_elements",
+    "c3_basic",
+    "c3_multitexture",
+    "c4_transformation",
+    "c5_depth_stencil",
+    "c6_framebuffers",
+    "c7_geometry_shader",
+    "c8_transform_feedback",
+];
+
+/// Sets up the chapter at `index` in `chapters`.
+fn start(index: uint, surface: &Surface) -> Result<~Running, ~str> {
+    match index {
+        0 => chapter::start::<c2_triangle::Triangle>(surface),
+        1 => chapter::start::<c2_triangle_uniform::TriangleUniform>(surface),
+        2 => chapter::start::<c2_color_triangle::ColorTriangle>(surface),
+        3 => chapter::start::<c2_triangle_elements::TriangleElements>(surface),
+        4 => chapter::start::<c3_basic::Basic>(surface),
+        5 => chapter::start::<c3_multitexture::Multitexture>(surface),
+        6 => chapter::start::<c4_transformation::Transformation>(surface),
+        7 => chapter::start::<c5_depth_stencil::DepthStencil>(surface),
+        8 => chapter::start::<c6_framebuffers::Framebuffers>(surface),
+        9 => chapter::start::<c7_geometry_shader::GeometryShader>(surface),
+        10 => chapter::start::<c8_transform_feedback::TransformFeedback>(surface),
+        _ => fail!(fmt!("No chapter %u", index)),
+    }
+}
+
+fn usage(program: &str) {
+    io::println(fmt!("Usage: %s [options] [CHAPTER]", program));
+    io::println("");
+    io::println("Options:");
+    io::println("    --size WIDTHxHEIGHT  The size of the window, 800x600 by default");
+    io::println("    --no-vsync           Swap buffers without waiting for the vertical blank");
+    io::println("    --samples N          Antialias with N samples per pixel");
+    io::println("    --fullscreen         Use the whole of the primary monitor");
+    io::println("");
+    io::println("Chapters, with the number keys that switch to them. The left and right");
+    io::println("arrows step through all of them:");
+    for chapters.eachi |i, &name| {
+        match chapter::number_key(i) {
+            Some(digit) => io::println(fmt!("    %u  %s", digit, name)),
+            None => io::println(fmt!("       %s", name)),
+        }
+    }
+}
+
+/// Parses a size such as `1024x768`.
+fn parse_size(size: &str) -> Option<(uint, uint)> {
+    match str::split_char(size, 'x') {
+        [ref width, ref height] => {
+            match (uint::from_str(*width), uint::from_str(*height)) {
+                (Some(width), Some(height)) if width > 0 && height > 0 => Some((width, height)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Prints `message` and the usage, and sets a failing exit status.
+fn error(program: &str, message: &str) {
+    io::stderr().write_line(message);
+    usage(program);
+    os::set_exit_status(1);
+}
+
+fn main() {
+    let args = os::args();
+    let program = copy args[0];
+    let opts = ~[
+        optopt("size"),
+        optflag("no-vsync"),
+        optopt("samples"),
+        optflag("fullscreen"),
+        optflag("help"),
+    ];
+    let matches = match getopts(args.tail(), opts) {
+        Ok(matches) => matches,
+        Err(f) => return error(program, fail_str(f)),
+    };
+
+    if opt_present(&matches, "help") {
+        return usage(program);
+    }
+
+    let first = match matches.free {
+        [] => 0,
+        [ref name] => match chapters.position(|&c| c == name.as_slice()) {
+            Some(index) => index,
+            None => return error(program, fmt!("Unknown chapter %s", *name)),
+        },
+        _ => return error(program, "Expected the name of at most one chapter"),
+    };
+
+    // Some chapters need depth and stencil buffers
+    let mut config = context::Config::default()
+        .with_title("OpenGL tutorials")
+        .with_depth_stencil(24, 8)
+        .with_vsync(!opt_present(&matches, "no-vsync"))
+        .with_fullscreen(opt_present(&matches, "fullscreen"));
+
+    match opt_maybe_str(&matches, "size") {
+        Some(size) => match parse_size(size) {
+            Some((width, height)) => config = config.with_size(width, height),
+            None => return error(program, fmt!("Invalid size %s", size)),
+        },
+        None => (),
+    }
+
+    match opt_maybe_str(&matches, "samples") {
+        Some(samples) => match uint::from_str(samples) {
+            Some(samples) => config = config.with_samples(samples),
+            None => return error(program, fmt!("Invalid sample count %s", samples)),
+        },
+        None => (),
+    }
+
+    do context::spawn(config) |window| {
+        chapter::run_switching(window, chapters.len(), first, start);
+    }
+}
@@ -0,0 +1,8 @@
+// The vertex attributes shared by the textured chapters
+#ifdef USE_3D
+in vec3 position;
+#else
+in vec2 position;
+#endif
+in vec3 color;
+in vec2 texcoord;
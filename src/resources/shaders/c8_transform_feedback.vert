@@ -0,0 +1,6 @@
+#version 150
+in float inValue;
+out float outValue;
+void main() {
+    outValue = sqrt(inValue);
+}
@@ -0,0 +1,46 @@
+#version 150
+in vec2 Texcoord;
+out vec4 outColor;
+uniform sampler2D texFramebuffer;
+
+// The texel at an offset of (x, y) texels from this one
+vec4 texelAt(float x, float y) {
+    vec2 size = vec2(textureSize(texFramebuffer, 0));
+    return texture(texFramebuffer, Texcoord + vec2(x, y) / size);
+}
+
+float luminance(vec4 color) {
+    return 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+}
+
+void main() {
+#if defined(INVERT)
+    outColor = vec4(1.0 - texelAt(0.0, 0.0).rgb, 1.0);
+#elif defined(GRAYSCALE)
+    outColor = vec4(vec3(luminance(texelAt(0.0, 0.0))), 1.0);
+#elif defined(BLUR)
+    // Average a 5x5 box around the texel
+    vec4 sum = vec4(0.0);
+    for (float x = -2.0; x <= 2.0; x += 1.0) {
+        for (float y = -2.0; y <= 2.0; y += 1.0) {
+            sum += texelAt(x, y);
+        }
+    }
+    outColor = sum / 25.0;
+#elif defined(EDGES)
+    // The Sobel operator over the luminance of the 3x3 neighbourhood
+    float tl = luminance(texelAt(-1.0,  1.0));
+    float t  = luminance(texelAt( 0.0,  1.0));
+    float tr = luminance(texelAt( 1.0,  1.0));
+    float l  = luminance(texelAt(-1.0,  0.0));
+    float r  = luminance(texelAt( 1.0,  0.0));
+    float bl = luminance(texelAt(-1.0, -1.0));
+    float b  = luminance(texelAt( 0.0, -1.0));
+    float br = luminance(texelAt( 1.0, -1.0));
+    float gx = (tr + 2.0 * r + br) - (tl + 2.0 * l + bl);
+    float gy = (tl + 2.0 * t + tr) - (bl + 2.0 * b + br);
+    outColor = vec4(vec3(length(vec2(gx, gy))), 1.0);
+#else
+    outColor = texelAt(0.0, 0.0);
+#endif
+}
@@ -0,0 +1,22 @@
+#version 150
+in vec3 Color;
+in vec2 Texcoord;
+out vec4 outColor;
+#ifdef MIX_TEXTURES
+uniform sampler2D texKitten;
+uniform sampler2D texPuppy;
+#else
+uniform sampler2D tex;
+#endif
+void main() {
+#ifdef MIX_TEXTURES
+    vec4 texColor = mix(texture(texKitten, Texcoord), texture(texPuppy, Texcoord), 0.5);
+#else
+    vec4 texColor = texture(tex, Texcoord);
+#endif
+#ifdef USE_COLOR
+    outColor = vec4(Color, 1.0) * texColor;
+#else
+    outColor = texColor;
+#endif
+}
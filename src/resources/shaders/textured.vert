@@ -0,0 +1,30 @@
+#version 150
+#include "attributes.glsl"
+out vec3 Color;
+out vec2 Texcoord;
+#ifdef USE_TRANSFORM
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 proj;
+#endif
+#ifdef USE_OVERRIDE_COLOR
+uniform vec3 overrideColor;
+#endif
+void main() {
+#ifdef USE_OVERRIDE_COLOR
+    Color = overrideColor * color;
+#else
+    Color = color;
+#endif
+    Texcoord = texcoord;
+#ifdef USE_3D
+    vec4 point = vec4(position, 1.0);
+#else
+    vec4 point = vec4(position, 0.0, 1.0);
+#endif
+#ifdef USE_TRANSFORM
+    gl_Position = proj * view * model * point;
+#else
+    gl_Position = point;
+#endif
+}
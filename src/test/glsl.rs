@@ -0,0 +1,198 @@
+//! Checks the GLSL preprocessor against the shared shaders in
+//! `src/resources/shaders`, and the diagnostics for shaders that fail to
+//! compile. The preprocessor tests need no GL driver, while the compile
+//! tests render nothing but need `libOSMesa` like the golden tests.
+//!
+//! Run with `cargo test --test glsl`.
+
+extern mod common;
+
+use common::context::Config;
+use common::glsl;
+use common::headless;
+use common::shader::*;
+
+fn lines(src: &str) -> ~[~str] {
+    let mut lines = ~[];
+    for str::each_line(src) |line| {
+        lines.push(line.to_owned());
+    }
+    lines
+}
+
+#[test]
+fn test_defines_follow_version() {
+    let processed = glsl::preprocess("#version 150\nvoid main() {}\n",
+                                     [(~"USE_TRANSFORM", ~"1")]).unwrap();
+    assert_eq!(lines(processed.source),
+               ~[~"#version 150", ~"#define USE_TRANSFORM 1", ~"#line 2 0", ~"void main() {}"]);
+}
+
+#[test]
+fn test_defines_without_version() {
+    let processed = glsl::preprocess("void main() {}\n", [(~"N", ~"4")]).unwrap();
+    assert_eq!(lines(processed.source), ~[~"#define N 4", ~"#line 1 0", ~"void main() {}"]);
+}
+
+#[test]
+fn test_include_is_expanded() {
+    let processed = glsl::preprocess("#version 150\n#include \"attributes.glsl\"\n", []).unwrap();
+    assert_eq!(processed.includes, ~[~"shaders/attributes.glsl"]);
+
+    let source = lines(processed.source);
+    assert_eq!(source[0], ~"#version 150");
+    assert!(source.contains(&~"in vec2 position;"));
+    assert!(!source.any(|line| line.starts_with("#include")));
+}
+
+#[test]
+fn test_include_lines_are_numbered() {
+    let src = "#version 150\n#include \"attributes.glsl\"\nvoid main() {}\n";
+    let processed = glsl::preprocess(src, []).unwrap();
+    assert_eq!(processed.sources.len(), 2);
+    assert_eq!(processed.sources[0], src.to_owned());
+
+    // The include is source string 1, and the line after it is line 3 of
+    // the source again
+    let source = lines(processed.source);
+    assert_eq!(source.slice(0, 2).to_owned(), ~[~"#version 150", ~"#line 1 1"]);
+    assert_eq!(source.slice(source.len() - 2, source.len()).to_owned(),
+               ~[~"#line 3 0", ~"void main() {}"]);
+}
+
+#[test]
+fn test_missing_include_is_an_error() {
+    let result = glsl::preprocess("#include \"missing.glsl\"\n", []);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_target_from_version() {
+    assert_eq!(glsl::Target::from_version("3.2.0 NVIDIA 310.44"), glsl::Glsl150);
+    assert_eq!(glsl::Target::from_version("3.3 (Core Profile) Mesa 10.1.0"), glsl::Glsl330);
+    assert_eq!(glsl::Target::from_version("4.1 ATI-1.14.21"), glsl::Glsl330);
+    assert_eq!(glsl::Target::from_version("OpenGL ES 3.0 Mesa 10.1.0"), glsl::Essl300);
+}
+
+#[test]
+fn test_retarget_replaces_version() {
+    let src = "#version 150\nout vec4 outColor;\n";
+    let outputs = [(0, ~"outColor")];
+
+    assert_eq!(lines(glsl::retarget(src, glsl::Glsl150, outputs)),
+               ~[~"#version 150", ~"out vec4 outColor;"]);
+    assert_eq!(lines(glsl::retarget(src, glsl::Glsl330, outputs)),
+               ~[~"#version 330", ~"layout(location = 0) out vec4 outColor;"]);
+    assert_eq!(lines(glsl::retarget(src, glsl::Essl300, outputs)),
+               ~[~"#version 300 es", ~"precision highp float;", ~"#line 2 0",
+                 ~"layout(location = 0) out vec4 outColor;"]);
+}
+
+#[test]
+fn test_retarget_adds_missing_version() {
+    // The header pushes the source down a line
+    assert_eq!(lines(glsl::retarget("void main() {}\n", glsl::Glsl330, [])),
+               ~[~"#version 330", ~"#line 1 0", ~"void main() {}"]);
+}
+
+#[test]
+fn test_retarget_rewrites_line_directives_for_glsl_150() {
+    // GLSL 1.50 numbers the line after `#line n` as n + 1
+    let src = "#version 150\n#define N 4\n#line 2 0\nvoid main() {}\n";
+    assert_eq!(lines(glsl::retarget(src, glsl::Glsl150, [])),
+               ~[~"#version 150", ~"#define N 4", ~"#line 1 0", ~"void main() {}"]);
+    assert_eq!(lines(glsl::retarget(src, glsl::Glsl330, [])),
+               ~[~"#version 330", ~"#define N 4", ~"#line 2 0", ~"void main() {}"]);
+}
+
+#[test]
+fn test_retarget_keeps_gles_lines_after_precision() {
+    // Defines and an include were added after the `#version` line
+    let src = "#version 150\n#define N 4\n#line 2 0\nvoid main() {}\n";
+    assert_eq!(lines(glsl::retarget(src, glsl::Essl300, [])),
+               ~[~"#version 300 es", ~"precision highp float;", ~"#line 2 0",
+                 ~"#define N 4", ~"#line 2 0", ~"void main() {}"]);
+}
+
+#[test]
+fn test_retarget_leaves_other_outputs() {
+    // Vertex shader outputs have no color number
+    assert_eq!(lines(glsl::retarget("out vec3 Color;\n", glsl::Glsl330, [(0, ~"outColor")])),
+               ~[~"#version 330", ~"#line 1 0", ~"out vec3 Color;"]);
+}
+
+/// Runs `f` with an offscreen context current, for the tests that compile
+/// shaders.
+fn with_context(f: &fn()) {
+    let _context = match headless::Context::create(&Config::default(), 0) {
+        Ok(context) => context,
+        Err(e) => fail!(e),
+    };
+    f();
+}
+
+#[test]
+fn test_errors_are_reported_at_file_lines() {
+    do with_context {
+        // The define and the include both add lines before the error
+        let result = ProgramBuilder::new()
+            .vertex("#version 150\n\
+                     #include \"attributes.glsl\"\n\
+                     void main() {\n\
+                         gl_Position = vec4(undeclared, 0.0, 1.0);\n\
+                     }\n")
+            .define("USE_3D", "1")
+            .build();
+        match result {
+            Err(CompileError(Vertex, diagnostics)) => {
+                let first = &diagnostics[0];
+                assert_eq!(first.line, Some(4));
+                assert_eq!(first.file, None);
+                assert_eq!(first.source_line, Some(~"gl_Position = vec4(undeclared, 0.0, 1.0);"));
+            }
+            Err(e) => fail!(e.to_str()),
+            Ok(_) => fail!(~"expected the vertex shader to fail to compile"),
+        }
+    }
+}
+
+#[test]
+fn test_every_failing_stage_is_reported() {
+    do with_context {
+        // The vertex and geometry shaders are broken, the fragment shader
+        // is fine
+        let result = ProgramBuilder::new()
+            .vertex("#version 150\n\
+                     void main() {\n\
+                         gl_Position = missing;\n\
+                     }\n")
+            .geometry("#version 150\n\
+                       layout(points) in;\n\
+                       layout(points, max_vertices = 1) out;\n\
+                       void main() {\n\
+                           EmitVertex()\n\
+                       }\n")
+            .fragment("#version 150\n\
+                       out vec4 outColor;\n\
+                       void main() {\n\
+                           outColor = vec4(1.0);\n\
+                       }\n")
+            .frag_data_location(0, "outColor")
+            .build();
+        match result {
+            Err(StageErrors(errors)) => {
+                let stages = errors.map(|e| match *e {
+                    CompileError(stage, _) => stage,
+                    _ => fail!(e.to_str()),
+                });
+                assert_eq!(stages, ~[Vertex, Geometry]);
+
+                let message = StageErrors(errors).to_str();
+                assert!(message.starts_with("Failed to compile vertex shader:\n"));
+                assert!(message.contains("\nFailed to compile geometry shader:\n"));
+            }
+            Err(e) => fail!(e.to_str()),
+            Ok(_) => fail!(~"expected two stages to fail to compile"),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Renders every chapter offscreen and compares the result with the
+//! reference images in `test/golden`. The 2D chapters are also compared with
+//! the output of the software rasterizer in `raster.rs`.
+//!
+//! Run with `cargo test --test golden` against `libOSMesa`, or build with
+//! `--test` and run from the `src` directory. Set `GOLDEN_UPDATE` to write
+//! new reference images instead of checking them.
+//! When an image doesn't match, the rendered frame and the expected one are
+//! written to `test/output` for comparison.
+
+extern mod common;
+extern mod glcore;
+extern mod glfw;
+extern mod lmath;
+extern mod numeric;
+extern mod stb_image;
+
+use core::libc::c_int;
+use common::chapter::Chapter;
+use common::context::{Config, Surface};
+use common::headless;
+use common::png;
+use common::resources;
+use raster::{Canvas, Color, Image};
+use stb_image::image;
+use stb_image::image::ImageU8;
+
+mod raster;
+
+#[path = "../c2_triangle.rs"]
+mod c2_triangle;
+#[path = "../c2_triangle_uniform.rs"]
+mod c2_triangle_uniform;
+#[path = "../c2_color_triangle.rs"]
+mod c2_color_triangle;
+#[path = "../c2_triangle_elements.rs"]
+mod c2_triangle_elements;
+#[path = "../c3_basic.rs"]
+mod c3_basic;
+#[path = "../c3_multitexture.rs"]
+mod c3_multitexture;
+#[path = "../c4_transformation.rs"]
+mod c4_transformation;
+#[path = "../c5_depth_stencil.rs"]
+mod c5_depth_stencil;
+#[path = "../c6_framebuffers.rs"]
+mod c6_framebuffers;
+#[path = "../c7_geometry_shader.rs"]
+mod c7_geometry_shader;
+#[path = "../c8_transform_feedback.rs"]
+mod c8_transform_feedback;
+
+static WIDTH: uint = 160;
+static HEIGHT: uint = 120;
+
+/// The difference in a color channel above which a pixel counts as changed.
+static TOLERANCE: u8 = 8;
+
+/// The fraction of pixels that may change, to allow for triangle edges
+/// being rasterized slightly differently between Mesa versions.
+static MAX_CHANGED: float = 0.005;
+
+/// The color that every chapter clears to.
+static CLEAR_COLOR: Color = [0.1, 0.1, 0.1, 1.0];
+
+/// Runs a chapter offscreen for `frames` frames, returning the last one.
+fn render(frames: uint, run: &fn(&Surface)) -> ~[u8] {
+    render_with(Config::default(), frames, run)
+}
+
+/// Like `render`, with a context described by `config`.
+fn render_with(config: Config, frames: uint, run: &fn(&Surface)) -> ~[u8] {
+    let config = config.with_size(WIDTH, HEIGHT);
+    let context = match headless::Context::create(&config, frames) {
+        Ok(context) => context,
+        Err(e) => fail!(e),
+    };
+    run(&context as &Surface);
+    context.read_pixels()
+}
+
+/// A headless surface that holds down `key` throughout, so that chapters
+/// see it pressed in the first frame.
+struct HoldKey {
+    context: headless::Context,
+    key: c_int,
+}
+
+impl Surface for HoldKey {
+    fn should_close(&self) -> bool { self.context.should_close() }
+    fn poll_events(&self) { self.context.poll_events() }
+    fn swap_buffers(&self) { self.context.swap_buffers() }
+    fn get_time(&self) -> f64 { self.context.get_time() }
+    fn get_size(&self) -> (uint, uint) { self.context.get_size() }
+    fn is_key_down(&self, key: c_int) -> bool { key == self.key }
+}
+
+/// Like `render`, holding down `key` from the first frame.
+fn render_pressing(key: c_int, frames: uint, run: &fn(&Surface)) -> ~[u8] {
+    let config = Config::default().with_size(WIDTH, HEIGHT);
+    let context = match headless::Context::create(&config, frames) {
+        Ok(context) => context,
+        Err(e) => fail!(e),
+    };
+    let surface = HoldKey { context: context, key: key };
+    run(&surface as &Surface);
+    surface.context.read_pixels()
+}
+
+fn check_golden(name: &str, pixels: &[u8]) {
+    let path = resources::root().push_many([~"test", ~"golden", fmt!("%s.png", name)]);
+
+    if os::getenv("GOLDEN_UPDATE").is_some() {
+        match png::write(&path, WIDTH, HEIGHT, pixels) {
+            Ok(()) => return,
+            Err(e) => fail!(e),
+        }
+    }
+
+    let golden = match image::load_with_depth(path.to_str(), 4, false) {
+        ImageU8(golden) => golden,
+        _ => fail!(fmt!("Missing reference image %s, run with GOLDEN_UPDATE=1 to create it",
+                        path.to_str())),
+    };
+    assert_eq!((golden.width, golden.height), (WIDTH, HEIGHT));
+
+    check_matches(name, pixels, golden.data);
+}
+
+/// Fails if more than `MAX_CHANGED` of the pixels differ from `expected`,
+/// after writing both images to `test/output`.
+fn check_matches(name: &str, pixels: &[u8], expected: &[u8]) {
+    let mut changed = 0u;
+    for uint::range(0, WIDTH * HEIGHT) |i| {
+        for uint::range(i * 4, i * 4 + 4) |c| {
+            let (a, b) = (pixels[c], expected[c]);
+            if (if a > b { a - b } else { b - a }) > TOLERANCE {
+                changed += 1;
+                break;
+            }
+        }
+    }
+
+    if changed as float > MAX_CHANGED * (WIDTH * HEIGHT) as float {
+        let dir = resources::root().push("test").push("output");
+        os::make_dir(&dir, 0o755);
+        let output = dir.push(fmt!("%s.png", name));
+        png::write(&output, WIDTH, HEIGHT, pixels);
+        png::write(&dir.push(fmt!("%s_expected.png", name)), WIDTH, HEIGHT, expected);
+        fail!(fmt!("%s: %u of %u pixels differ from the reference, see %s",
+                   name, changed, WIDTH * HEIGHT, output.to_str()));
+    }
+}
+
+#[test]
+fn test_c2_triangle() {
+    check_golden("c2_triangle", render(1, c2_triangle::run));
+}
+
+#[test]
+fn test_c2_triangle_uniform() {
+    // Part way through the pulse of red
+    check_golden("c2_triangle_uniform", render(16, c2_triangle_uniform::run));
+}
+
+#[test]
+fn test_c2_color_triangle() {
+    check_golden("c2_color_triangle", render(1, c2_color_triangle::run));
+}
+
+#[test]
+fn test_c2_triangle_elements() {
+    check_golden("c2_triangle_elements", render(1, c2_triangle_elements::run));
+}
+
+#[test]
+fn test_c3_basic() {
+    check_golden("c3_basic", render(1, c3_basic::run));
+}
+
+#[test]
+fn test_c3_multitexture() {
+    check_golden("c3_multitexture", render(1, c3_multitexture::run));
+}
+
+// Frames are 1/60th of a second apart, and the last frame is drawn at
+// `(frames - 1) / 60` seconds
+
+#[test]
+fn test_c4_transformation_at_0s() {
+    check_golden("c4_transformation_0s", render(1, c4_transformation::run));
+}
+
+#[test]
+fn test_c4_transformation_at_quarter_s() {
+    check_golden("c4_transformation_0.25s", render(16, c4_transformation::run));
+}
+
+#[test]
+fn test_c4_transformation_at_half_s() {
+    check_golden("c4_transformation_0.5s", render(31, c4_transformation::run));
+}
+
+#[test]
+fn test_c5_depth_stencil() {
+    // A quarter of a second in, part way through a turn
+    let config = Config::default().with_depth_stencil(24, 8);
+    check_golden("c5_depth_stencil", render_with(config, 16, c5_depth_stencil::run));
+}
+
+#[test]
+fn test_c6_framebuffers() {
+    // The same scene as c5_depth_stencil, drawn through a framebuffer with
+    // no effect. The surface itself needs no depth or stencil buffer.
+    check_golden("c6_framebuffers", render(16, c6_framebuffers::run));
+}
+
+#[test]
+fn test_c6_framebuffers_inverted() {
+    // The I key switches to the effect before the first frame is drawn
+    check_golden("c6_framebuffers_invert",
+                 render_pressing(glfw::KEY_I, 16, c6_framebuffers::run));
+}
+
+#[test]
+fn test_c6_framebuffers_blurred() {
+    check_golden("c6_framebuffers_blur",
+                 render_pressing(glfw::KEY_B, 16, c6_framebuffers::run));
+}
+
+#[test]
+fn test_c7_geometry_shader() {
+    check_golden("c7_geometry_shader", render(1, c7_geometry_shader::run));
+}
+
+#[test]
+fn test_c8_transform_feedback_computes_square_roots() {
+    // Nothing is drawn, so the outputs are checked instead of an image
+    let config = Config::default().with_size(WIDTH, HEIGHT);
+    let context = match headless::Context::create(&config, 1) {
+        Ok(context) => context,
+        Err(e) => fail!(e),
+    };
+    let chapter: c8_transform_feedback::TransformFeedback = match Chapter::setup(&context as &Surface) {
+        Ok(chapter) => chapter,
+        Err(e) => fail!(e),
+    };
+
+    let outputs = chapter.compute();
+    assert_eq!(outputs.len(), c8_transform_feedback::inputs.len());
+    for c8_transform_feedback::inputs.eachi |i, &input| {
+        let expected = f32::sqrt(input);
+        assert!(f32::abs(outputs[i] - expected) < 1e-5,
+                fmt!("sqrt(%?) came back as %?", input, outputs[i]));
+    }
+}
+
+// The 2D chapters drawn by the software rasterizer, with the vertex and
+// fragment shaders rewritten in Rust. Comparing these with the GPU checks
+// that the `Color` and `Texcoord` varyings are interpolated as expected.
+
+fn reference(draw: &fn(&mut Canvas)) -> ~[u8] {
+    let mut canvas = Canvas::new(WIDTH, HEIGHT);
+    canvas.clear(CLEAR_COLOR);
+    draw(&mut canvas);
+    canvas.to_rgba()
+}
+
+#[test]
+fn test_c2_triangle_matches_reference() {
+    let expected = do reference |canvas| {
+        canvas.draw_arrays(c2_triangle::vertices,
+                           |v| (v.position, ~[]),
+                           |_| [1.0, 1.0, 1.0, 1.0]);
+    };
+    check_matches("c2_triangle_raster", render(1, c2_triangle::run), expected);
+}
+
+#[test]
+fn test_c2_color_triangle_matches_reference() {
+    let expected = do reference |canvas| {
+        canvas.draw_arrays(c2_color_triangle::vertices,
+                           |v| (v.position, v.color.to_owned()),
+                           |color| [color[0], color[1], color[2], 1.0]);
+    };
+    check_matches("c2_color_triangle_raster", render(1, c2_color_triangle::run), expected);
+}
+
+#[test]
+fn test_c2_triangle_elements_matches_reference() {
+    let elements = c2_triangle_elements::elements.map(|&i| i as uint);
+    let expected = do reference |canvas| {
+        canvas.draw_elements(c2_triangle_elements::vertices, elements,
+                             |v| (v.position, v.color.to_owned()),
+                             |color| [color[0], color[1], color[2], 1.0]);
+    };
+    check_matches("c2_triangle_elements_raster", render(1, c2_triangle_elements::run), expected);
+}
+
+#[test]
+fn test_c3_basic_matches_reference() {
+    let path = match resources::path("sample.png") {
+        Some(path) => path.to_str(),
+        None => fail!(~"Could not find sample.png"),
+    };
+    let image = match Image::load(path) {
+        Ok(image) => image,
+        Err(e) => fail!(e.to_str()),
+    };
+    let elements = c3_basic::elements.map(|&i| i as uint);
+    let expected = do reference |canvas| {
+        canvas.draw_elements(c3_basic::vertices, elements,
+                             |v| (v.position, ~[v.color[0], v.color[1], v.color[2],
+                                               v.texcoord[0], v.texcoord[1]]),
+                             |f| {
+            let texel = image.sample(f[3], f[4]);
+            [texel[0] * f[0], texel[1] * f[1], texel[2] * f[2], texel[3]]
+        });
+    };
+    check_matches("c3_basic_raster", render(1, c3_basic::run), expected);
+}
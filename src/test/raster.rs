@@ -0,0 +1,223 @@
+//! A small software rasterizer, used by the tests as a reference for what
+//! the 2D chapters should draw.
+//!
+//! Triangles are filled following the GL rules: a pixel is covered when its
+//! center is inside the triangle, and pixels on a shared edge belong to the
+//! triangle whose top or left edge it is. Varyings are interpolated linearly
+//! in screen space, which matches GL for the chapters since every vertex has
+//! a `w` of 1.
+
+use stb_image::image;
+use stb_image::image::ImageU8;
+use common::texture::{LoadError, NotFound, DecodeError};
+
+pub type Color = [f32, ..4];
+
+/// An RGBA image in memory that triangles are drawn to.
+pub struct Canvas {
+    width: uint,
+    height: uint,
+    /// Rows from the bottom up, as in GL window coordinates
+    priv pixels: ~[Color],
+}
+
+/// A vertex after the vertex stage, with its position in normalized device
+/// coordinates and the values to interpolate across the triangle.
+struct Transformed {
+    x: f32,
+    y: f32,
+    varyings: ~[f32],
+}
+
+impl Canvas {
+    pub fn new(width: uint, height: uint) -> Canvas {
+        Canvas {
+            width: width,
+            height: height,
+            pixels: vec::from_elem(width * height, [0.0f32, 0.0, 0.0, 0.0]),
+        }
+    }
+
+    /// Fills every pixel with `color`, like `glClear`.
+    pub fn clear(&mut self, color: Color) {
+        for uint::range(0, self.pixels.len()) |i| {
+            self.pixels[i] = color;
+        }
+    }
+
+    /// Draws `vertices` as a list of triangles, like `glDrawArrays` with
+    /// `GL_TRIANGLES`.
+    pub fn draw_arrays<V>(&mut self, vertices: &[V],
+                          vertex: &fn(&V) -> ([f32, ..2], ~[f32]),
+                          fragment: &fn(&[f32]) -> Color) {
+        let indices = vec::from_fn(vertices.len(), |i| i);
+        self.draw_elements(vertices, indices, vertex, fragment);
+    }
+
+    /// Draws the triangles made by each three `indices` into `vertices`,
+    /// like `glDrawElements` with `GL_TRIANGLES`. `vertex` returns the
+    /// position and varyings of a vertex, and `fragment` the color of a
+    /// pixel given the interpolated varyings.
+    pub fn draw_elements<V>(&mut self, vertices: &[V], indices: &[uint],
+                            vertex: &fn(&V) -> ([f32, ..2], ~[f32]),
+                            fragment: &fn(&[f32]) -> Color) {
+        let transformed = do vertices.map |v| {
+            let (position, varyings) = vertex(v);
+            Transformed { x: position[0], y: position[1], varyings: varyings }
+        };
+
+        for uint::range(0, indices.len() / 3) |i| {
+            self.fill(&transformed[indices[i * 3]],
+                      &transformed[indices[i * 3 + 1]],
+                      &transformed[indices[i * 3 + 2]],
+                      fragment);
+        }
+    }
+
+    fn fill(&mut self, a: &Transformed, b: &Transformed, c: &Transformed,
+            fragment: &fn(&[f32]) -> Color) {
+        let (w, h) = (self.width as f32, self.height as f32);
+        let to_window = |v: &Transformed| ((v.x + 1.0) * 0.5 * w, (v.y + 1.0) * 0.5 * h);
+        let p0 = to_window(a);
+        let mut p1 = to_window(b);
+        let mut p2 = to_window(c);
+        let mut v1 = b;
+        let mut v2 = c;
+
+        // Wind the triangle counter-clockwise, so that the inside is to the
+        // left of every edge
+        if edge(p0, p1, p2) < 0.0 {
+            util::swap(&mut p1, &mut p2);
+            util::swap(&mut v1, &mut v2);
+        }
+        let area = edge(p0, p1, p2);
+        if area == 0.0 { return; }
+
+        // The pixels whose centers could be inside
+        let ((x0, y0), (x1, y1), (x2, y2)) = (p0, p1, p2);
+        let min_x = f32::max(0.0, f32::floor(fmin(x0, x1, x2))) as uint;
+        let max_x = f32::min(w, f32::ceil(fmax(x0, x1, x2))) as uint;
+        let min_y = f32::max(0.0, f32::floor(fmin(y0, y1, y2))) as uint;
+        let max_y = f32::min(h, f32::ceil(fmax(y0, y1, y2))) as uint;
+
+        let mut varyings = vec::from_elem(a.varyings.len(), 0.0f32);
+        for uint::range(min_y, max_y) |y| {
+            for uint::range(min_x, max_x) |x| {
+                let p = (x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge(p1, p2, p);
+                let w1 = edge(p2, p0, p);
+                let w2 = edge(p0, p1, p);
+                if !covers(w0, p1, p2) || !covers(w1, p2, p0) || !covers(w2, p0, p1) {
+                    loop;
+                }
+
+                let (w0, w1, w2) = (w0 / area, w1 / area, w2 / area);
+                for uint::range(0, varyings.len()) |i| {
+                    varyings[i] = w0 * a.varyings[i] + w1 * v1.varyings[i] + w2 * v2.varyings[i];
+                }
+                self.pixels[y * self.width + x] = fragment(varyings);
+            }
+        }
+    }
+
+    /// The pixels as RGBA bytes with the top row first, in the same layout
+    /// as `headless::read_pixels`.
+    pub fn to_rgba(&self) -> ~[u8] {
+        let mut bytes = vec::with_capacity(self.pixels.len() * 4);
+        for uint::range(0, self.height) |y| {
+            let row = (self.height - 1 - y) * self.width;
+            for uint::range(row, row + self.width) |i| {
+                for self.pixels[i].each |&c| {
+                    bytes.push(to_byte(c));
+                }
+            }
+        }
+        bytes
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`, which is positive
+/// when `p` is to the left of the edge from `a` to `b`.
+fn edge(a: (f32, f32), b: (f32, f32), p: (f32, f32)) -> f32 {
+    let ((ax, ay), (bx, by), (px, py)) = (a, b, p);
+    (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+}
+
+/// Whether a pixel with edge value `w` for the counter-clockwise edge from
+/// `a` to `b` is inside. Pixels exactly on the edge are only inside for
+/// top and left edges.
+fn covers(w: f32, a: (f32, f32), b: (f32, f32)) -> bool {
+    if w != 0.0 { return w > 0.0; }
+    let ((ax, ay), (bx, by)) = (a, b);
+    let top = ay == by && bx < ax;
+    let left = by < ay;
+    top || left
+}
+
+fn fmin(a: f32, b: f32, c: f32) -> f32 { f32::min(a, f32::min(b, c)) }
+fn fmax(a: f32, b: f32, c: f32) -> f32 { f32::max(a, f32::max(b, c)) }
+
+/// Converts a color channel to a byte the way GL does for normalized
+/// framebuffers.
+fn to_byte(c: f32) -> u8 {
+    let c = f32::max(0.0, f32::min(1.0, c));
+    f32::floor(c * 255.0 + 0.5) as u8
+}
+
+/// An RGBA image that can be sampled like a texture with the default
+/// `texture::Options`: clamped to the edges with linear filtering.
+pub struct Image {
+    width: uint,
+    height: uint,
+    /// Rows from `t = 0` up
+    priv data: ~[u8],
+}
+
+impl Image {
+    pub fn load(path: &str) -> Result<Image, LoadError> {
+        if !os::path_exists(&Path(path)) {
+            return Err(NotFound(path.to_owned()));
+        }
+
+        match image::load_with_depth(path.to_owned(), 4, false) {
+            ImageU8(image) => Ok(Image {
+                width: image.width,
+                height: image.height,
+                data: copy image.data,
+            }),
+            _ => Err(DecodeError(path.to_owned())),
+        }
+    }
+
+    /// Samples the image at texture coordinates `(s, t)`, like `texture()`
+    /// in GLSL.
+    pub fn sample(&self, s: f32, t: f32) -> Color {
+        // Texel centers are at half coordinates
+        let u = s * self.width as f32 - 0.5;
+        let v = t * self.height as f32 - 0.5;
+        let (x0, y0) = (f32::floor(u), f32::floor(v));
+        let (fx, fy) = (u - x0, v - y0);
+
+        let mut color = [0.0f32, 0.0, 0.0, 0.0];
+        for [(0, 0, (1.0 - fx) * (1.0 - fy)), (1, 0, fx * (1.0 - fy)),
+             (0, 1, (1.0 - fx) * fy),         (1, 1, fx * fy)].each |&(dx, dy, weight)| {
+            let texel = self.texel(x0 as int + dx, y0 as int + dy);
+            for uint::range(0, 4) |i| {
+                color[i] += weight * texel[i];
+            }
+        }
+        color
+    }
+
+    /// The texel at `(x, y)`, clamped to the edges.
+    fn texel(&self, x: int, y: int) -> Color {
+        let x = int::max(0, int::min(x, self.width as int - 1)) as uint;
+        let y = int::max(0, int::min(y, self.height as int - 1)) as uint;
+        let i = (y * self.width + x) * 4;
+        let mut color = [0.0f32, 0.0, 0.0, 0.0];
+        for uint::range(0, 4) |c| {
+            color[c] = self.data[i + c] as f32 / 255.0;
+        }
+        color
+    }
+}
@@ -0,0 +1,469 @@
+//! Checks the GL calls that chapters make, using the recording backend so
+//! that no GL driver is needed.
+//!
+//! Run with `cargo test --test recording`.
+
+extern mod common;
+extern mod glcore;
+extern mod glfw;
+
+use core::libc::c_int;
+use common::chapter;
+use common::chapter::{Chapter, Frame, Running};
+use common::context::Surface;
+use common::gl;
+use common::gl::Gl;
+use common::recording::*;
+use common::shader::*;
+use common::tracking::Tracking;
+use common::vertex::{Layout, OutsideVertex};
+use glcore::*;
+
+#[path = "../c2_triangle.rs"]
+mod c2_triangle;
+#[path = "../c2_triangle_elements.rs"]
+mod c2_triangle_elements;
+#[path = "../c3_basic.rs"]
+mod c3_basic;
+#[path = "../c3_multitexture.rs"]
+mod c3_multitexture;
+#[path = "../c5_depth_stencil.rs"]
+mod c5_depth_stencil;
+#[path = "../c6_framebuffers.rs"]
+mod c6_framebuffers;
+#[path = "../c7_geometry_shader.rs"]
+mod c7_geometry_shader;
+#[path = "../c8_transform_feedback.rs"]
+mod c8_transform_feedback;
+
+/// A surface that closes after a single frame.
+struct OneFrame {
+    frame: @mut uint,
+}
+
+impl Surface for OneFrame {
+    fn should_close(&self) -> bool { *self.frame >= 1 }
+    fn poll_events(&self) {}
+    fn swap_buffers(&self) { *self.frame += 1; }
+    fn get_time(&self) -> f64 { 0.0 }
+    fn get_size(&self) -> (uint, uint) { (800, 600) }
+    fn is_key_down(&self, _: c_int) -> bool { false }
+}
+
+/// A surface that holds down `key` during frame 1, and closes after frame
+/// 2.
+struct PressKey {
+    key: c_int,
+    frame: @mut uint,
+}
+
+impl Surface for PressKey {
+    fn should_close(&self) -> bool { *self.frame >= 3 }
+    fn poll_events(&self) {}
+    fn swap_buffers(&self) { *self.frame += 1; }
+    fn get_time(&self) -> f64 { *self.frame as f64 }
+    fn get_size(&self) -> (uint, uint) { (800, 600) }
+    fn is_key_down(&self, key: c_int) -> bool { key == self.key && *self.frame == 1 }
+}
+
+/// A surface that holds down `keys[i]` during frame `i + 1`, and closes
+/// after the frame following the last key.
+struct PressKeys {
+    keys: ~[c_int],
+    frame: @mut uint,
+}
+
+impl Surface for PressKeys {
+    fn should_close(&self) -> bool { *self.frame > self.keys.len() + 1 }
+    fn poll_events(&self) {}
+    fn swap_buffers(&self) { *self.frame += 1; }
+    fn get_time(&self) -> f64 { *self.frame as f64 }
+    fn get_size(&self) -> (uint, uint) { (800, 600) }
+    fn is_key_down(&self, key: c_int) -> bool {
+        *self.frame >= 1 && *self.frame <= self.keys.len() && key == self.keys[*self.frame - 1]
+    }
+}
+
+/// Runs a chapter for one frame, returning the calls it made.
+fn record(run: &fn(&Surface)) -> @Recording {
+    let recording = @Recording::new();
+    record_with(recording, run);
+    recording
+}
+
+/// Runs a chapter for one frame, recording its calls to `recording`.
+fn record_with(recording: @Recording, run: &fn(&Surface)) {
+    let surface = OneFrame { frame: @mut 0 };
+    do gl::with(recording as @Gl) {
+        run(&surface as &Surface);
+    }
+}
+
+#[test]
+fn test_triangle_elements_uploads_indices_once() {
+    let recording = record(c2_triangle_elements::run);
+
+    // 6 `GLuint` indices for the two triangles
+    let uploads = recording.filter(|c| match *c {
+        BufferData(GL_ELEMENT_ARRAY_BUFFER, _, _) => true,
+        _ => false,
+    });
+    assert_eq!(uploads, ~[BufferData(GL_ELEMENT_ARRAY_BUFFER, 24, GL_STATIC_DRAW)]);
+
+    // 4 vertices of a 2D position and an RGB color
+    let uploads = recording.filter(|c| match *c {
+        BufferData(GL_ARRAY_BUFFER, _, _) => true,
+        _ => false,
+    });
+    assert_eq!(uploads, ~[BufferData(GL_ARRAY_BUFFER, 80, GL_STATIC_DRAW)]);
+}
+
+#[test]
+fn test_triangle_elements_draws_once_per_frame() {
+    let recording = record(c2_triangle_elements::run);
+
+    let draws = recording.filter(|c| match *c {
+        DrawElements(*) | DrawArrays(*) => true,
+        _ => false,
+    });
+    assert_eq!(draws, ~[DrawElements(GL_TRIANGLES, 6, GL_UNSIGNED_INT, 0)]);
+}
+
+#[test]
+fn test_triangle_elements_draws_each_time_it_is_asked() {
+    let recording = @Recording::new();
+    do gl::with(recording as @Gl) {
+        let surface = OneFrame { frame: @mut 0 };
+        let mut chapter: c2_triangle_elements::TriangleElements =
+            match Chapter::setup(&surface as &Surface) {
+                Ok(chapter) => chapter,
+                Err(e) => fail!(e),
+            };
+
+        // Driven directly, as a benchmark would, without a surface loop
+        for uint::range(0, 3) |i| {
+            let frame = Frame { time: i as f64, delta: 1.0, width: 800, height: 600,
+                               pressed: ~[] };
+            chapter.update(&frame);
+            chapter.draw(&frame);
+        }
+        chapter.teardown();
+    }
+
+    let draws = recording.filter(|c| match *c { DrawElements(*) => true, _ => false });
+    assert_eq!(draws.len(), 3);
+}
+
+#[test]
+fn test_depth_stencil_masks_the_reflection_with_the_floor() {
+    let recording = record(c5_depth_stencil::run);
+
+    // The cube, the floor, then the reflection of the cube
+    let draws = recording.filter(|c| match *c { DrawArrays(*) => true, _ => false });
+    assert_eq!(draws, ~[DrawArrays(GL_TRIANGLES, 0, 36),
+                        DrawArrays(GL_TRIANGLES, 36, 6),
+                        DrawArrays(GL_TRIANGLES, 0, 36)]);
+
+    // The floor writes 1 to the stencil, and the reflection is only drawn
+    // there
+    let funcs = recording.filter(|c| match *c { StencilFunc(*) => true, _ => false });
+    assert_eq!(funcs, ~[StencilFunc(GL_ALWAYS, 1, 0xFF), StencilFunc(GL_EQUAL, 1, 0xFF)]);
+
+    // Both tests are left off for the next chapter
+    let calls = recording.calls();
+    assert!(calls.contains(&Disable(GL_STENCIL_TEST)));
+    assert!(calls.contains(&Disable(GL_DEPTH_TEST)));
+}
+
+#[test]
+fn test_framebuffers_draws_the_scene_into_a_texture() {
+    let recording = record(c6_framebuffers::run);
+
+    // A color texture and a depth and stencil renderbuffer
+    let attachments = recording.filter(|c| match *c {
+        FramebufferTexture2D(*) | FramebufferRenderbuffer(*) => true,
+        _ => false,
+    });
+    assert_eq!(attachments.len(), 2);
+    assert!(recording.calls().contains(&RenderbufferStorage(GL_RENDERBUFFER, GL_DEPTH24_STENCIL8,
+                                                            800, 600)));
+
+    // The scene goes to the framebuffer, and the quad to the surface
+    let targets = recording.filter(|c| match *c {
+        BindFramebuffer(*) | DrawArrays(*) => true,
+        _ => false,
+    });
+    // Bound first when it is set up
+    let framebuffer = match targets[0] {
+        BindFramebuffer(GL_FRAMEBUFFER, name) if name != 0 => name,
+        ref call => fail!(fmt!("expected the framebuffer to be bound first, not %?", *call)),
+    };
+    assert_eq!(targets.slice(targets.len() - 6, targets.len()).to_owned(),
+               ~[BindFramebuffer(GL_FRAMEBUFFER, framebuffer),
+                 DrawArrays(GL_TRIANGLES, 0, 36),
+                 DrawArrays(GL_TRIANGLES, 36, 6),
+                 DrawArrays(GL_TRIANGLES, 0, 36),
+                 BindFramebuffer(GL_FRAMEBUFFER, 0),
+                 DrawArrays(GL_TRIANGLES, 0, 6)]);
+}
+
+#[test]
+fn test_framebuffers_builds_each_effect_once() {
+    let recording = @Recording::new();
+    let surface = PressKey { key: glfw::KEY_E, frame: @mut 0 };
+    do gl::with(recording as @Gl) {
+        chapter::run::<c6_framebuffers::Framebuffers>(&surface as &Surface);
+    }
+
+    // The scene, the quad without an effect, then edge detection from
+    // frame 1 on
+    let programs = recording.filter(|c| match *c { CreateProgram(*) => true, _ => false });
+    assert_eq!(programs.len(), 3);
+    let edges = recording.filter(|c| match *c {
+        ShaderSource(_, ref src) => src.contains("#define EDGES 1"),
+        _ => false,
+    });
+    assert_eq!(edges.len(), 2);
+}
+
+#[test]
+fn test_geometry_shader_is_attached_between_the_other_stages() {
+    let recording = record(c7_geometry_shader::run);
+
+    let stages = recording.filter(|c| match *c { CreateShader(*) => true, _ => false });
+    let types = stages.map(|c| match *c { CreateShader(ty, _) => ty, _ => fail!() });
+    assert_eq!(types, ~[GL_VERTEX_SHADER, GL_GEOMETRY_SHADER, GL_FRAGMENT_SHADER]);
+
+    let draws = recording.filter(|c| match *c { DrawArrays(*) => true, _ => false });
+    assert_eq!(draws, ~[DrawArrays(GL_POINTS, 0, 4)]);
+}
+
+#[test]
+fn test_transform_feedback_captures_without_rasterizing() {
+    let recording = record(c8_transform_feedback::run);
+
+    let varyings = recording.filter(|c| match *c { TransformFeedbackVaryings(*) => true, _ => false });
+    let names = varyings.map(|c| match *c {
+        TransformFeedbackVaryings(_, ref names, mode) => (copy *names, mode),
+        _ => fail!(),
+    });
+    assert_eq!(names, ~[(~[~"outValue"], GL_INTERLEAVED_ATTRIBS)]);
+
+    // The output buffer is the last one created
+    let buffers = recording.filter(|c| match *c { GenBuffer(*) => true, _ => false });
+    let tbo = match buffers.last() {
+        &GenBuffer(name) => name,
+        ref call => fail!(fmt!("expected a buffer, not %?", *call)),
+    };
+
+    // Rasterizing is off while the points are captured, and the results
+    // are read back afterwards
+    let feedback = recording.filter(|c| match *c {
+        Enable(cap) | Disable(cap) => cap == GL_RASTERIZER_DISCARD,
+        BindBufferBase(*) | BeginTransformFeedback(*) | EndTransformFeedback |
+        DrawArrays(*) | GetBufferSubData(*) => true,
+        _ => false,
+    });
+    assert_eq!(feedback, ~[Enable(GL_RASTERIZER_DISCARD),
+                           BindBufferBase(GL_TRANSFORM_FEEDBACK_BUFFER, 0, tbo),
+                           BeginTransformFeedback(GL_POINTS),
+                           DrawArrays(GL_POINTS, 0, 5),
+                           EndTransformFeedback,
+                           Disable(GL_RASTERIZER_DISCARD),
+                           GetBufferSubData(GL_TRANSFORM_FEEDBACK_BUFFER, 0, 20)]);
+}
+
+#[test]
+fn test_viewport_covers_the_surface() {
+    let recording = record(c2_triangle_elements::run);
+
+    let viewports = recording.filter(|c| match *c { Viewport(*) => true, _ => false });
+    assert_eq!(viewports, ~[Viewport(0, 0, 800, 600)]);
+}
+
+#[test]
+fn test_unchanged_shaders_are_not_rebuilt() {
+    let recording = @Recording::new();
+    do gl::with(recording as @Gl) {
+        let surface = OneFrame { frame: @mut 0 };
+        let mut chapter: c2_triangle_elements::TriangleElements =
+            match Chapter::setup(&surface as &Surface) {
+                Ok(chapter) => chapter,
+                Err(e) => fail!(e),
+            };
+        for uint::range(0, 3) |i| {
+            chapter.update(&Frame { time: i as f64, delta: 1.0, width: 800, height: 600,
+                                    pressed: ~[] });
+        }
+    }
+
+    let programs = recording.filter(|c| match *c { CreateProgram(*) => true, _ => false });
+    assert_eq!(programs.len(), 1);
+}
+
+#[test]
+fn test_program_cache_builds_each_permutation_once() {
+    let recording = @Recording::new();
+    do gl::with(recording as @Gl) {
+        let textured = || {
+            ProgramBuilder::new()
+                .vertex_file("shaders/textured.vert")
+                .fragment_file("shaders/textured.frag")
+        };
+        let mut cache = ProgramCache::new();
+        cache.get(&textured()).unwrap();
+        cache.get(&textured().define("MIX_TEXTURES", "1")).unwrap();
+        cache.get(&textured().define("MIX_TEXTURES", "1")).unwrap();
+        cache.get(&textured()).unwrap();
+    }
+
+    let programs = recording.filter(|c| match *c { CreateProgram(*) => true, _ => false });
+    assert_eq!(programs.len(), 2);
+}
+
+/// A vertex with its fields in a different order to the layout.
+struct Reordered {
+    color: [GLfloat, ..3],
+    position: [GLfloat, ..2],
+}
+
+static reordered: Reordered = Reordered { color: [1.0, 1.0, 1.0], position: [0.0, 0.0] };
+
+#[test]
+fn test_layout_offsets_come_from_the_fields() {
+    let recording = @Recording::new();
+    do gl::with(recording as @Gl) {
+        let program = ProgramBuilder::new().vertex("void main() {}").build().unwrap();
+        let v = &reordered;
+        Layout::new(v).attrib("position", &v.position).attrib("color", &v.color)
+            .bind(&program).unwrap();
+    }
+
+    let pointers = recording.filter(|c| match *c { VertexAttribPointer(*) => true, _ => false });
+    assert_eq!(pointers, ~[VertexAttribPointer(0, 2, GL_FLOAT, false, 20, 12),
+                           VertexAttribPointer(1, 3, GL_FLOAT, false, 20, 0)]);
+}
+
+#[test]
+fn test_layout_rejects_fields_of_another_value() {
+    let recording = @Recording::new();
+    do gl::with(recording as @Gl) {
+        let program = ProgramBuilder::new().vertex("void main() {}").build().unwrap();
+        let other = Reordered { color: [0.0, 0.0, 0.0], position: [0.0, 0.0] };
+        let v = &reordered;
+        match Layout::new(v).attrib("position", &other.position).attrib("color", &v.color)
+                .bind(&program) {
+            Err(OutsideVertex(name)) => assert_eq!(name, ~"position"),
+            _ => fail!(~"expected the position to be outside the vertex"),
+        }
+    }
+}
+
+#[test]
+fn test_gles_outputs_are_located_in_the_source() {
+    let recording = @Recording::with_version("OpenGL ES 3.0 Mesa 10.1.0");
+    record_with(recording, c2_triangle_elements::run);
+
+    let bindings = recording.filter(|c| match *c { BindFragDataLocation(*) => true, _ => false });
+    assert_eq!(bindings, ~[]);
+
+    for recording.calls().each |call| {
+        match *call {
+            ShaderSource(_, ref src) => {
+                assert!(src.starts_with("#version 300 es\n"));
+                assert!(!src.contains("#version 150"));
+            }
+            _ => (),
+        }
+    }
+}
+
+#[test]
+fn test_triangle_elements_deletes_everything() {
+    let recording = record(c2_triangle_elements::run);
+
+    let mut created = ~[];
+    let mut deleted = ~[];
+    for recording.calls().each |call| {
+        match *call {
+            GenBuffer(name) | GenVertexArray(name) | CreateShader(_, name) |
+            CreateProgram(name) => created.push(name),
+            DeleteBuffer(name) | DeleteVertexArray(name) | DeleteShader(name) |
+            DeleteProgram(name) => deleted.push(name),
+            _ => (),
+        }
+    }
+    assert_eq!(created.len(), deleted.len());
+    for created.each |name| {
+        assert!(deleted.contains(name));
+    }
+}
+
+#[test]
+fn test_switching_tears_down_the_previous_chapter() {
+    let recording = @Recording::new();
+    let tracking = @Tracking::new(recording as @Gl);
+    let surface = PressKey { key: glfw::KEY_RIGHT, frame: @mut 0 };
+
+    let start: &fn(uint, &Surface) -> Result<~Running, ~str> = |index, surface| {
+        match index {
+            0 => chapter::start::<c2_triangle_elements::TriangleElements>(surface),
+            _ => chapter::start::<c2_triangle::Triangle>(surface),
+        }
+    };
+    do gl::with(tracking as @Gl) {
+        chapter::run_switching(&surface as &Surface, 2, 0, start);
+    }
+
+    // Frame 0 is drawn by the first chapter, and frames 1 and 2 by the second
+    let draws = recording.filter(|c| match *c {
+        DrawElements(*) | DrawArrays(*) => true,
+        _ => false,
+    });
+    assert_eq!(draws, ~[DrawElements(GL_TRIANGLES, 6, GL_UNSIGNED_INT, 0),
+                        DrawArrays(GL_TRIANGLES, 0, 3),
+                        DrawArrays(GL_TRIANGLES, 0, 3)]);
+
+    // Every object of the first chapter is deleted before the second
+    // chapter creates its own
+    let calls = recording.calls();
+    let first_delete = calls.position(|c| match *c { DeleteProgram(*) => true, _ => false });
+    let last_create = calls.rposition(|c| match *c { CreateProgram(*) => true, _ => false });
+    assert!(first_delete.get() < last_create.get());
+
+    assert_eq!(tracking.live(), ~[]);
+}
+
+#[test]
+fn test_switching_back_samples_the_texture_of_the_chapter() {
+    let recording = @Recording::new();
+    let tracking = @Tracking::new(recording as @Gl);
+    let surface = PressKeys { keys: ~[glfw::KEY_RIGHT, glfw::KEY_LEFT], frame: @mut 0 };
+
+    let start: &fn(uint, &Surface) -> Result<~Running, ~str> = |index, surface| {
+        match index {
+            0 => chapter::start::<c3_basic::Basic>(surface),
+            _ => chapter::start::<c3_multitexture::Multitexture>(surface),
+        }
+    };
+    do gl::with(tracking as @Gl) {
+        chapter::run_switching(&surface as &Surface, 2, 0, start);
+    }
+
+    // Replay the texture bindings up to the last draw, which is made by
+    // c3_basic again after c3_multitexture bound its second texture
+    let mut active = GL_TEXTURE0;
+    let mut bound = ~[0 as GLuint, 0];
+    let mut uploaded = 0;
+    let unit = |active: GLenum| (active - GL_TEXTURE0) as uint;
+    for recording.calls().each |call| {
+        match *call {
+            ActiveTexture(texture_unit) => active = texture_unit,
+            BindTexture(GL_TEXTURE_2D, texture) => bound[unit(active)] = texture,
+            TexImage2D(*) => uploaded = bound[unit(active)],
+            _ => (),
+        }
+    }
+    assert_eq!(active, GL_TEXTURE0);
+    assert_eq!(bound[0], uploaded);
+}